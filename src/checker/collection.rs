@@ -1,20 +1,40 @@
-use std::{ffi::OsStr, fs, path::Path};
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
 
 use base64::{engine::general_purpose, Engine};
 use color_eyre::eyre::{bail, eyre, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::Serialize;
 
+use super::{
+    extras::{classify_extra, extra_target},
+    verify::verify_before_link,
+};
 use crate::{
     config::{Collection, Link, Matcher, SeasonFolder, SpecialMapping},
-    dl::{Client, Torrent},
+    dl::{Client, Kind, ServerTorrents, Source},
     get_url_bytes,
-    parser::{self},
+    parser::{self, Episode, TitleRules},
     VIDEO_EXTS,
 };
 
+// 文件夹名, 不区分大小写
+static SPECIAL_FOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^(?:SPs?|Specials?|特典|映像特典)$").unwrap());
+static IGNORED_FOLDER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(?:CDs?|Scans?|Fonts?|OSTs?|Music|Soundtracks?|Booklets?)$").unwrap()
+});
+
 pub async fn check_collection(
     collection: &Collection,
     dl_client: &mut dyn Client,
-    dl_server_torrents: &[Torrent],
+    dl_server_torrents: &ServerTorrents,
     added_torrent_hashs: &mut Vec<String>,
     maybe_link: &Option<Link>,
 ) -> Result<()> {
@@ -22,28 +42,45 @@ pub async fn check_collection(
         name,
         torrent_url,
         title,
-        season_folders,
-        special_mappings,
         external_subtitle,
+        ..
     } = collection;
+    let title_rules = title_rules(collection)?;
+    let file_filter = FileFilter::new(collection)?;
     let bytes = get_url_bytes(torrent_url).await?;
     let torrent = lava_torrent::torrent::v1::Torrent::read_from_bytes(&bytes)?;
     // If the torrent contains only 1 file then files is None.
-    if torrent.files.is_none() {
+    let Some(files) = &torrent.files else {
         bail!("不是多文件种子: {}", title);
-    }
-
-    let some_server_torrent = dl_server_torrents
+    };
+    let wanted = files
         .iter()
-        .find(|t| t.hash == torrent.info_hash());
-    if let Some(server_torrent) = some_server_torrent {
+        .map(|f| file_filter.is_wanted(&f.path))
+        .collect::<Vec<_>>();
+
+    let source = Source::new(Kind::Collection, name);
+    if let Some(server_torrent) = dl_server_torrents.find(&torrent.info_hash()) {
+        // 修改了 include/exclude 后同步到下载器, 等下次检查再链接
+        if !server_torrent.wanted.is_empty() && server_torrent.wanted != wanted {
+            let (files_wanted, files_unwanted) = split_wanted(&wanted);
+            dl_client
+                .torrent_set_files_wanted(server_torrent, &files_wanted, &files_unwanted)
+                .await?;
+            println!("更新需要下载的文件: {}", title);
+            return Ok(());
+        }
         if let Some(link_config) = maybe_link {
             if link_config.enable && server_torrent.percent_done >= 1.0 {
-                for file in torrent
-                    .files
-                    .as_ref()
-                    .ok_or_else(|| eyre!("torrent has only one file: {title}"))?
-                {
+                let season_folders = season_folders(
+                    collection,
+                    files
+                        .iter()
+                        .zip(&wanted)
+                        .filter(|(_, w)| **w)
+                        .map(|(f, _)| f.path.as_path()),
+                    &title_rules,
+                );
+                for (file, _) in files.iter().zip(&wanted).filter(|(_, w)| **w) {
                     let file_name_from_torrent = file
                         .path
                         .file_name()
@@ -63,64 +100,21 @@ pub async fn check_collection(
                         .and_then(OsStr::to_str)
                         .ok_or_else(|| eyre!("get file_stem & to_str failed: {:?}", file.path))?;
 
-                    let season;
-                    let link_file_name;
-
-                    if let Some(SpecialMapping { name, matcher, .. }) =
-                        special_mappings.iter().find(|sm| match &sm.matcher {
-                            Matcher::Off => sm.file_name == file_name_from_torrent,
-                            Matcher::On(regex) => regex.is_match(file_name_from_torrent),
-                        })
-                    {
-                        season = &0_u8;
-                        match matcher {
-                            Matcher::Off => link_file_name = name.to_string(),
-                            Matcher::On(regex) => {
-                                if let Some(captures) = regex.captures(file_name_from_torrent) {
-                                    let mut name = name.to_string();
-                                    for (i, cap) in captures.iter().enumerate() {
-                                        if i == 0 {
-                                            continue;
-                                        }
-                                        if let Some(cap) = cap {
-                                            name = name.replace(&format!("{{{i}}}"), cap.as_str())
-                                        }
-                                    }
-                                    link_file_name = name;
-                                } else {
-                                    link_file_name = name.to_string();
-                                }
-                            }
-                        }
-                    } else {
-                        let parent =
-                            file.path.parent().and_then(Path::to_str).ok_or_else(|| {
-                                eyre!("get parent & to_str failed: {:?}", file.path)
-                            })?;
-                        let maybe_season = if let Some(SeasonFolder { season, .. }) =
-                            season_folders.iter().find(|sf| sf.folder == parent)
-                        {
-                            Some(season)
-                        } else {
-                            None
-                        };
-                        if maybe_season.is_none() {
-                            continue;
-                        }
-                        season = maybe_season.unwrap(); // is_none checked
-                        let maybe_real_ep = parser::process(file_name_from_torrent);
-                        if maybe_real_ep.is_err() {
-                            println!(
-                                "{file_name_from_torrent} 解析失败: {}",
-                                maybe_real_ep.unwrap_err() // is_err checked
-                            );
-                            continue;
-                        }
-                        let real_ep = maybe_real_ep.unwrap(); // is_err checked
-                        link_file_name = real_ep.link_file_name_with_season(name, season);
-                    }
+                    let Some(LinkTarget {
+                        path,
+                        link_file_name,
+                        crc32,
+                    }) = link_target(
+                        &file.path,
+                        collection,
+                        &season_folders,
+                        &title_rules,
+                        Some(link_config),
+                    )?
+                    else {
+                        continue;
+                    };
 
-                    let path = parser::link_path(name, season);
                     let full_path = format!("{}/{path}", &link_config.path);
                     let full_file_name = format!("{}.{file_suffix}", link_file_name);
                     let link = format!("{full_path}/{full_file_name}");
@@ -136,6 +130,16 @@ pub async fn check_collection(
                         if link_config.dry_run {
                             println!("准备链接{link} <- {original}",);
                         } else {
+                            if !verify_before_link(
+                                &original,
+                                crc32.as_deref(),
+                                &link_file_name,
+                                link_config,
+                            )
+                            .await?
+                            {
+                                continue;
+                            }
                             fs::create_dir_all(&full_path)?;
                             match fs::hard_link(&original, &link) {
                                 Ok(_) => {
@@ -166,15 +170,511 @@ pub async fn check_collection(
 
         return Ok(());
     }
+    if dl_server_torrents.is_unmanaged(&torrent.info_hash()) {
+        println!("下载器中已有手动添加的种子, 跳过: {}", title);
+        return Ok(());
+    }
     if added_torrent_hashs.contains(&torrent.info_hash()) {
         println!("{} 刚刚已经被加入下载了", title);
         return Ok(());
     }
+    let (_, files_unwanted) = split_wanted(&wanted);
     dl_client
-        .torrent_add_by_meta(general_purpose::STANDARD.encode(bytes), name)
+        .torrent_add_by_meta_with_files(
+            general_purpose::STANDARD.encode(bytes),
+            &source,
+            &files_unwanted,
+        )
         .await?;
     added_torrent_hashs.push(torrent.info_hash());
     println!("加入下载列表: {}", title);
 
     Ok(())
 }
+
+/// include/exclude 的glob, 匹配种子中的文件路径
+struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl FileFilter {
+    fn new(collection: &Collection) -> Result<Self> {
+        let include = if collection.include.is_empty() {
+            None
+        } else {
+            Some(glob_set(&collection.include)?)
+        };
+        Ok(FileFilter {
+            include,
+            exclude: glob_set(&collection.exclude)?,
+        })
+    }
+
+    fn is_wanted(&self, path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|i| i.is_match(path)) && !self.exclude.is_match(path)
+    }
+}
+
+pub(super) fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| eyre!("glob格式错误 {pattern}: {e}"))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+/// 把每个文件是否需要下载转换为 (需要下载的文件序号, 不需要下载的文件序号)
+fn split_wanted(wanted: &[bool]) -> (Vec<usize>, Vec<usize>) {
+    (0..wanted.len()).partition(|i| wanted[*i])
+}
+
+fn title_rules(collection: &Collection) -> Result<TitleRules> {
+    TitleRules::new(
+        collection.episode_regex.as_deref(),
+        collection.season_regex.as_deref(),
+        &collection.title_rewrite,
+    )
+}
+
+/// 配置了 season_folders 时使用配置, 否则自动推断
+fn season_folders<'a>(
+    collection: &Collection,
+    files: impl Iterator<Item = &'a Path>,
+    title_rules: &TitleRules,
+) -> Vec<SeasonFolder> {
+    if collection.season_folders.is_empty() {
+        infer_season_folders(files, title_rules).0
+    } else {
+        collection.season_folders.clone()
+    }
+}
+
+/**
+ * 根据文件夹名和其中的视频文件名推断season, 返回 (season_folders, 被忽略的文件夹)
+ * 1. SPs Specials 特典 等为 season 0
+ * 2. CDs Scans Fonts 等附带内容直接忽略
+ * 3. 文件夹名中有 S2 Season 2 第二季 等时使用文件夹名中的season
+ * 4. 否则使用文件夹中第一个能解析出集数的视频文件的season
+ */
+pub fn infer_season_folders<'a>(
+    files: impl Iterator<Item = &'a Path>,
+    title_rules: &TitleRules,
+) -> (Vec<SeasonFolder>, Vec<String>) {
+    // 文件夹 -> 其中的视频文件名
+    let mut folders = BTreeMap::<String, Vec<&str>>::new();
+    for path in files {
+        let (Some(file_name), Some(ext)) = (
+            path.file_name().and_then(OsStr::to_str),
+            path.extension().and_then(OsStr::to_str),
+        ) else {
+            continue;
+        };
+        if VIDEO_EXTS.iter().all(|e| *e != ext) {
+            continue;
+        }
+        let Some(folder) = path.parent().and_then(Path::to_str) else {
+            continue;
+        };
+        folders
+            .entry(folder.to_string())
+            .or_default()
+            .push(file_name);
+    }
+
+    let mut season_folders = vec![];
+    let mut ignored = vec![];
+    for (folder, file_names) in folders {
+        let folder_name = folder.rsplit('/').next().unwrap_or_default();
+        let season = if IGNORED_FOLDER_RE.is_match(folder_name) {
+            None
+        } else if SPECIAL_FOLDER_RE.is_match(folder_name) {
+            Some(0)
+        } else if let Some(season) = parser::find_season(folder_name) {
+            Some(season)
+        } else {
+            file_names
+                .iter()
+                .find_map(|file_name| match title_rules.process(file_name) {
+                    Ok(ep @ Episode::Ep(_)) => Some(ep.season()),
+                    _ => None,
+                })
+        };
+        match season {
+            Some(season) => season_folders.push(SeasonFolder { season, folder }),
+            None => ignored.push(folder),
+        }
+    }
+    (season_folders, ignored)
+}
+
+/// 种子中一个视频文件的链接目标
+struct LinkTarget {
+    /// 相对于链接目录的文件夹
+    path: String,
+    link_file_name: String,
+    crc32: Option<String>,
+}
+
+/**
+ * 有多季时特典所在或上级的季文件夹对应的季, 有多个时取最近的
+ * 根目录和 SPs 等 S00 的文件夹不确定属于哪一季, 返回None
+ */
+fn extra_season(season_folders: &[SeasonFolder], parent: &str) -> Option<u8> {
+    let seasons = season_folders.iter().filter(|sf| sf.season != 0);
+    if seasons
+        .clone()
+        .map(|sf| sf.season)
+        .collect::<HashSet<_>>()
+        .len()
+        < 2
+    {
+        return None;
+    }
+    seasons
+        .filter(|sf| {
+            sf.folder == parent
+                || (!sf.folder.is_empty() && parent.starts_with(&format!("{}/", sf.folder)))
+        })
+        .max_by_key(|sf| sf.folder.len())
+        .filter(|sf| !sf.folder.is_empty())
+        .map(|sf| sf.season)
+}
+
+/// 按 special_mappings 和 season_folders 计算链接目标, 需要跳过的文件返回None
+fn link_target(
+    path: &Path,
+    collection: &Collection,
+    season_folders: &[SeasonFolder],
+    title_rules: &TitleRules,
+    link_config: Option<&Link>,
+) -> Result<Option<LinkTarget>> {
+    let file_name_from_torrent = path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| eyre!("get file_name & to_str failed: {:?}", path))?;
+    let name = &collection.name;
+
+    if let Some(SpecialMapping { name, matcher, .. }) =
+        collection
+            .special_mappings
+            .iter()
+            .find(|sm| match &sm.matcher {
+                Matcher::Off => sm.file_name == file_name_from_torrent,
+                Matcher::On(regex) => regex.is_match(file_name_from_torrent),
+            })
+    {
+        let link_file_name = match matcher {
+            Matcher::Off => name.to_string(),
+            Matcher::On(regex) => {
+                if let Some(captures) = regex.captures(file_name_from_torrent) {
+                    let mut name = name.to_string();
+                    for (i, cap) in captures.iter().enumerate() {
+                        if i == 0 {
+                            continue;
+                        }
+                        if let Some(cap) = cap {
+                            name = name.replace(&format!("{{{i}}}"), cap.as_str())
+                        }
+                    }
+                    name
+                } else {
+                    name.to_string()
+                }
+            }
+        };
+        return Ok(Some(LinkTarget {
+            path: parser::link_path(name, &0),
+            link_file_name,
+            crc32: None,
+        }));
+    }
+
+    let parent = path
+        .parent()
+        .and_then(Path::to_str)
+        .ok_or_else(|| eyre!("get parent & to_str failed: {:?}", path))?;
+
+    let file_stem = path
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| eyre!("get file_stem & to_str failed: {:?}", path))?;
+    let in_special_folder =
+        SPECIAL_FOLDER_RE.is_match(parent.rsplit('/').next().unwrap_or_default());
+    if let Some(target) = classify_extra(file_stem, in_special_folder).and_then(|extra| {
+        extra_target(
+            collection.extras,
+            name,
+            extra_season(season_folders, parent),
+            &extra,
+        )
+    }) {
+        return Ok(target.map(|(path, link_file_name)| LinkTarget {
+            path,
+            link_file_name,
+            crc32: None,
+        }));
+    }
+
+    let Some(SeasonFolder { season, .. }) = season_folders.iter().find(|sf| sf.folder == parent)
+    else {
+        return Ok(None);
+    };
+    let real_ep = match title_rules.process(file_name_from_torrent) {
+        Ok(ep) => ep,
+        Err(e) => {
+            println!("{file_name_from_torrent} 解析失败: {e}");
+            return Ok(None);
+        }
+    };
+    let min_confidence = link_config.map_or(0, |l| l.min_confidence);
+    if real_ep.confidence() < min_confidence {
+        println!(
+            "{file_name_from_torrent} 解析的可信度过低: {}",
+            real_ep.confidence()
+        );
+        return Ok(None);
+    }
+    let crc32 = real_ep.crc32().map(str::to_string);
+    let link_file_name = match link_config.and_then(|l| l.file_name_template.as_ref()) {
+        Some(template) => real_ep
+            .with_season(*season)
+            .link_file_name_by_template(template, name),
+        None => real_ep.link_file_name_with_season(name, season),
+    };
+    Ok(Some(LinkTarget {
+        path: parser::link_path(name, season),
+        link_file_name,
+        crc32,
+    }))
+}
+
+/// collection 保存前的预览
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CollectionPreview {
+    /// 实际使用的 season_folders, 没有配置时为自动推断的结果
+    pub season_folders: Vec<SeasonFolder>,
+    /// 有视频文件但是被忽略的文件夹
+    pub ignored_folders: Vec<String>,
+    /// (种子中的文件路径, 相对于链接目录的路径)
+    pub links: Vec<(String, String)>,
+}
+
+/// 下载种子并计算每个视频文件的链接目标, 不会修改任何文件
+pub async fn preview_collection(
+    collection: &Collection,
+    maybe_link: &Option<Link>,
+) -> Result<CollectionPreview> {
+    let bytes = get_url_bytes(&collection.torrent_url).await?;
+    let torrent = lava_torrent::torrent::v1::Torrent::read_from_bytes(&bytes)?;
+    let Some(files) = &torrent.files else {
+        bail!("不是多文件种子: {}", collection.title);
+    };
+    let file_filter = FileFilter::new(collection)?;
+    let paths = files
+        .iter()
+        .filter(|f| file_filter.is_wanted(&f.path))
+        .map(|f| f.path.clone())
+        .collect::<Vec<_>>();
+    preview_files(collection, &paths, maybe_link.as_ref())
+}
+
+fn preview_files(
+    collection: &Collection,
+    paths: &[PathBuf],
+    link_config: Option<&Link>,
+) -> Result<CollectionPreview> {
+    let title_rules = title_rules(collection)?;
+    let (inferred, ignored_folders) =
+        infer_season_folders(paths.iter().map(PathBuf::as_path), &title_rules);
+    let season_folders = if collection.season_folders.is_empty() {
+        inferred
+    } else {
+        collection.season_folders.clone()
+    };
+
+    let mut links = vec![];
+    for path in paths {
+        let Some(ext) = path.extension().and_then(OsStr::to_str) else {
+            continue;
+        };
+        if VIDEO_EXTS.iter().all(|e| *e != ext) {
+            continue;
+        }
+        if let Some(target) =
+            link_target(path, collection, &season_folders, &title_rules, link_config)?
+        {
+            links.push((
+                path.to_string_lossy().to_string(),
+                format!("{}/{}.{ext}", target.path, target.link_file_name),
+            ));
+        }
+    }
+    Ok(CollectionPreview {
+        season_folders,
+        ignored_folders,
+        links,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ExtrasMode;
+
+    #[test]
+    fn test_preview_files() {
+        let collection = toml::from_str::<Collection>(
+            r#"
+            torrent_url = ""
+            name = "物语"
+            title = ""
+            "#,
+        )
+        .unwrap();
+        let paths = [
+            "[VCB-Studio] Monogatari [01][Ma10p_1080p][x265_flac].mkv",
+            "[VCB-Studio] Monogatari [01][Ma10p_1080p][x265_flac].sc.ass",
+            "Season 2/[VCB-Studio] Monogatari S2 [03][Ma10p_1080p][x265_flac].mkv",
+            "第三季/[VCB-Studio] Monogatari [05][Ma10p_1080p][x265_flac].mkv",
+            "SPs/[VCB-Studio] Monogatari [NCOP][Ma10p_1080p][x265_flac].mkv",
+            "CDs/[VCB-Studio] Monogatari OST [01].mkv",
+            "Scans/01.jpg",
+        ]
+        .map(PathBuf::from);
+        let preview = preview_files(&collection, &paths, None).unwrap();
+        assert_eq!(
+            preview.season_folders,
+            vec![
+                SeasonFolder {
+                    season: 1,
+                    folder: "".to_string()
+                },
+                SeasonFolder {
+                    season: 0,
+                    folder: "SPs".to_string()
+                },
+                SeasonFolder {
+                    season: 2,
+                    folder: "Season 2".to_string()
+                },
+                SeasonFolder {
+                    season: 3,
+                    folder: "第三季".to_string()
+                },
+            ]
+        );
+        assert_eq!(preview.ignored_folders, vec!["CDs".to_string()]);
+        assert_eq!(
+            preview.links,
+            vec![
+                (
+                    paths[0].to_string_lossy().to_string(),
+                    "物语/Season 01/物语 S01E1.mkv".to_string()
+                ),
+                (
+                    paths[2].to_string_lossy().to_string(),
+                    "物语/Season 02/物语 S02E3.mkv".to_string()
+                ),
+                (
+                    paths[3].to_string_lossy().to_string(),
+                    "物语/Season 03/物语 S03E5.mkv".to_string()
+                ),
+                (
+                    paths[4].to_string_lossy().to_string(),
+                    "物语/Season 00/[VCB-Studio] Monogatari [NCOP][Ma10p_1080p][x265_flac].mkv"
+                        .to_string()
+                ),
+            ]
+        );
+
+        // 特典链接到 extras 文件夹
+        let preview = preview_files(
+            &Collection {
+                extras: ExtrasMode::Folders,
+                ..collection.clone()
+            },
+            &paths,
+            None,
+        )
+        .unwrap();
+        assert_eq!(preview.links[3].1, "物语/extras/物语 NCOP.mkv".to_string());
+
+        // 配置了 season_folders 时不推断
+        let collection = Collection {
+            season_folders: vec![SeasonFolder {
+                season: 1,
+                folder: "".to_string(),
+            }],
+            ..collection
+        };
+        let preview = preview_files(&collection, &paths, None).unwrap();
+        assert_eq!(preview.links.len(), 1);
+    }
+
+    #[test]
+    fn test_extra_season() {
+        let folder = |season, folder: &str| SeasonFolder {
+            season,
+            folder: folder.to_string(),
+        };
+        let two_seasons = [folder(1, "S1"), folder(2, "S2")];
+        assert_eq!(extra_season(&two_seasons, "S1/SPs"), Some(1));
+        assert_eq!(extra_season(&two_seasons, "S2"), Some(2));
+        assert_eq!(extra_season(&two_seasons, "SPs"), None);
+        assert_eq!(extra_season(&[folder(1, "S1")], "S1/SPs"), None);
+        // 根目录和 S00 的文件夹
+        let with_root = [folder(1, ""), folder(0, "SPs"), folder(2, "Season 2")];
+        assert_eq!(extra_season(&with_root, "SPs"), None);
+        assert_eq!(extra_season(&with_root, ""), None);
+        assert_eq!(extra_season(&with_root, "Season 2/SPs"), Some(2));
+    }
+
+    #[test]
+    fn test_file_filter() {
+        let paths = [
+            "[VCB-Studio] Monogatari [01][Ma10p_1080p][x265_flac].mkv",
+            "[VCB-Studio] Monogatari [01][Ma10p_1080p][x265_flac_aac].mka",
+            "[VCB-Studio] Monogatari [01][Audio Commentary].mka",
+            "SPs/[VCB-Studio] Monogatari [NCOP][Ma10p_1080p][x265_flac].mkv",
+            "CDs/[VCB-Studio] Monogatari OST/01.flac",
+            "Scans/01.jpg",
+        ]
+        .map(PathBuf::from);
+        let wanted = |include: &[&str], exclude: &[&str]| {
+            let collection = Collection {
+                include: include.iter().map(|s| s.to_string()).collect(),
+                exclude: exclude.iter().map(|s| s.to_string()).collect(),
+                ..Default::default()
+            };
+            let filter = FileFilter::new(&collection).unwrap();
+            paths
+                .iter()
+                .map(|p| filter.is_wanted(p))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(wanted(&[], &[]), vec![true; 6]);
+        assert_eq!(
+            wanted(&[], &["cds/**", "Scans/**", "*commentary*"]),
+            vec![true, true, false, true, false, false]
+        );
+        assert_eq!(
+            wanted(&["*.mkv", "*.ass"], &["SPs/**"]),
+            vec![true, false, false, false, false, false]
+        );
+        assert_eq!(
+            split_wanted(&[true, false, true, false]),
+            (vec![0, 2], vec![1, 3])
+        );
+        assert!(FileFilter::new(&Collection {
+            exclude: vec!["[".to_string()],
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{config::ExtrasMode, parser};
+
+/*
+   VCB-Studio 等BD合集中的特典, 一般是单独的块, 如
+   [SP01] [NCOP] [NCED2] [NCED_EP02] [Menu01] [Menu1-2] [PV] [CM03] [Preview01]
+   需要整块匹配, 避免把 [Spy x Family] 之类的名字当作SP
+*/
+static EXTRA_BLOCK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(SP|OVA|OAD|Special|NC ?OP|NC ?ED|PV|CM|SPOT|Trailer|Teaser|Preview|予告|Menu|Making|Interview|Event|Live|Talk)(?:[ _\-.]?(?:EP)?(\d{1,3}))?(?:[-_]\d{1,3})?(?:[vV]\d)?$")
+        .unwrap()
+});
+static BLOCK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]+)\]").unwrap());
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ExtraKind {
+    /// SP OVA OAD, 作为S00
+    Special,
+    /// 无字幕OP/ED
+    Creditless,
+    /// PV CM 预告
+    Trailer,
+    Menu,
+    /// 制作花絮 采访 活动
+    Featurette,
+}
+
+impl ExtraKind {
+    /// Jellyfin / Plex 识别的附加内容文件夹, Special 没有文件夹
+    pub fn folder(&self) -> Option<&'static str> {
+        match self {
+            ExtraKind::Special => None,
+            ExtraKind::Creditless | ExtraKind::Menu => Some("extras"),
+            ExtraKind::Trailer => Some("trailers"),
+            ExtraKind::Featurette => Some("featurettes"),
+        }
+    }
+}
+
+/// 特典的类型, 以及文件名中的标记(如 NCOP1)和编号
+#[derive(Debug, PartialEq)]
+pub struct Extra {
+    pub kind: ExtraKind,
+    pub tag: String,
+    pub number: Option<u32>,
+}
+
+/// 从文件名的 [] 块中识别特典, 在 SPs 之类的文件夹中又识别不出类型时视为 Special
+pub fn classify_extra(file_stem: &str, in_special_folder: bool) -> Option<Extra> {
+    let extra = BLOCK_RE
+        .captures_iter(file_stem)
+        .filter_map(|c| c.get(1))
+        .find_map(|block| {
+            let c = EXTRA_BLOCK_RE.captures(block.as_str())?;
+            let kind = match c[1].to_lowercase().replace(' ', "").as_str() {
+                "sp" | "ova" | "oad" | "special" => ExtraKind::Special,
+                "ncop" | "nced" => ExtraKind::Creditless,
+                "pv" | "cm" | "spot" | "trailer" | "teaser" | "preview" | "予告" => {
+                    ExtraKind::Trailer
+                }
+                "menu" => ExtraKind::Menu,
+                _ => ExtraKind::Featurette,
+            };
+            Some(Extra {
+                kind,
+                tag: block.as_str().to_string(),
+                number: c.get(2).and_then(|n| n.as_str().parse().ok()),
+            })
+        });
+    match extra {
+        Some(extra) => Some(extra),
+        None if in_special_folder => Some(Extra {
+            kind: ExtraKind::Special,
+            tag: file_stem.to_string(),
+            number: None,
+        }),
+        None => None,
+    }
+}
+
+/**
+ * 按 extras 的配置计算特典的 (相对链接目录的路径, 不带扩展名的文件名)
+ * 外层None表示不是特典, 内层None表示跳过
+ * - folders: Special 为 S00, 其他链接到 extras trailers featurettes 文件夹
+ * - specials: 全部作为 S00, 有编号的SP为 `{name} S00E{n}`, 其他保留标记
+ *
+ * 有多季时 season 为特典所在的季, 文件名为 `{name} S{season} {标记}`, 不同季的同名特典不会链接到同一个文件
+ */
+pub fn extra_target(
+    mode: ExtrasMode,
+    name: &str,
+    season: Option<u8>,
+    extra: &Extra,
+) -> Option<Option<(String, String)>> {
+    let tagged = match season {
+        Some(season) => format!("{name} S{season:0>2} {}", extra.tag),
+        None => format!("{name} {}", extra.tag),
+    };
+    let special = |tagged: String| {
+        let link_file_name = match (extra.kind, extra.number, season) {
+            (ExtraKind::Special, Some(n), None) => parser::link_file_name(name, &0, &n),
+            _ => tagged,
+        };
+        Some((parser::link_path(name, &0), link_file_name))
+    };
+    match mode {
+        ExtrasMode::Off => None,
+        ExtrasMode::Skip => Some(None),
+        ExtrasMode::Specials => Some(special(tagged)),
+        ExtrasMode::Folders => Some(match extra.kind.folder() {
+            Some(folder) => Some((format!("{name}/{folder}"), tagged)),
+            None => special(tagged),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extra(kind: ExtraKind, tag: &str, number: Option<u32>) -> Option<Extra> {
+        Some(Extra {
+            kind,
+            tag: tag.to_string(),
+            number,
+        })
+    }
+
+    #[test]
+    fn test_classify_extra() {
+        assert_eq!(
+            classify_extra(
+                "[VCB-Studio] Bocchi the Rock! [SP01][Ma10p_1080p][x265_flac]",
+                false
+            ),
+            extra(ExtraKind::Special, "SP01", Some(1))
+        );
+        assert_eq!(
+            classify_extra(
+                "[VCB-Studio] Bocchi the Rock! [NCOP][Ma10p_1080p][x265_flac]",
+                false
+            ),
+            extra(ExtraKind::Creditless, "NCOP", None)
+        );
+        assert_eq!(
+            classify_extra(
+                "[VCB-Studio] Bocchi the Rock! [NCED_EP02][Ma10p_1080p][x265_flac]",
+                false
+            ),
+            extra(ExtraKind::Creditless, "NCED_EP02", Some(2))
+        );
+        assert_eq!(
+            classify_extra(
+                "[VCB-Studio] Bocchi the Rock! [Menu01][Ma10p_1080p][x265_flac]",
+                false
+            ),
+            extra(ExtraKind::Menu, "Menu01", Some(1))
+        );
+        assert_eq!(
+            classify_extra(
+                "[VCB-Studio] Bocchi the Rock! [CM03][Ma10p_1080p][x265_flac]",
+                false
+            ),
+            extra(ExtraKind::Trailer, "CM03", Some(3))
+        );
+        assert_eq!(
+            classify_extra(
+                "[VCB-Studio] Bocchi the Rock! [01][Ma10p_1080p][x265_flac]",
+                false
+            ),
+            None
+        );
+        assert_eq!(
+            classify_extra("[Sakurato] Spy x Family [01][1080p]", false),
+            None
+        );
+        assert_eq!(
+            classify_extra("Bocchi the Rock! Live", true),
+            extra(ExtraKind::Special, "Bocchi the Rock! Live", None)
+        );
+    }
+
+    #[test]
+    fn test_extra_target() {
+        let sp = extra(ExtraKind::Special, "SP01", Some(1)).unwrap();
+        let pv = extra(ExtraKind::Trailer, "PV", None).unwrap();
+        assert_eq!(extra_target(ExtrasMode::Off, "孤独摇滚", None, &pv), None);
+        assert_eq!(
+            extra_target(ExtrasMode::Skip, "孤独摇滚", None, &pv),
+            Some(None)
+        );
+        assert_eq!(
+            extra_target(ExtrasMode::Folders, "孤独摇滚", None, &pv),
+            Some(Some((
+                "孤独摇滚/trailers".to_string(),
+                "孤独摇滚 PV".to_string()
+            )))
+        );
+        assert_eq!(
+            extra_target(ExtrasMode::Folders, "孤独摇滚", None, &sp),
+            Some(Some((
+                "孤独摇滚/Season 00".to_string(),
+                "孤独摇滚 S00E1".to_string()
+            )))
+        );
+        assert_eq!(
+            extra_target(ExtrasMode::Specials, "孤独摇滚", None, &pv),
+            Some(Some((
+                "孤独摇滚/Season 00".to_string(),
+                "孤独摇滚 PV".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_extra_target_seasons() {
+        let ncop = extra(ExtraKind::Creditless, "NCOP", None).unwrap();
+        let sp = extra(ExtraKind::Special, "SP01", Some(1)).unwrap();
+        let target =
+            |season, extra| extra_target(ExtrasMode::Folders, "孤独摇滚", Some(season), extra);
+        assert_eq!(
+            target(1, &ncop),
+            Some(Some((
+                "孤独摇滚/extras".to_string(),
+                "孤独摇滚 S01 NCOP".to_string()
+            )))
+        );
+        assert_eq!(
+            target(2, &ncop),
+            Some(Some((
+                "孤独摇滚/extras".to_string(),
+                "孤独摇滚 S02 NCOP".to_string()
+            )))
+        );
+        assert_ne!(target(1, &sp), target(2, &sp));
+        assert_eq!(
+            target(2, &sp),
+            Some(Some((
+                "孤独摇滚/Season 00".to_string(),
+                "孤独摇滚 S02 SP01".to_string()
+            )))
+        );
+    }
+}
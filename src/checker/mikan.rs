@@ -6,13 +6,15 @@ use std::{
 
 use base64::{engine::general_purpose, Engine};
 use bytes::Bytes;
-use color_eyre::eyre::{eyre, Result};
+use color_eyre::eyre::{bail, eyre, Result};
+use regex::Regex;
 
+use super::verify::verify_before_link;
 use crate::{
-    config::{Link, Mikan},
-    dl::{Client, Torrent},
+    config::{Link, Mikan, TagFilter},
+    dl::{Client, Kind, ServerTorrents, Source},
     get_url_bytes,
-    parser::{self, Episode},
+    parser::{self, Episode, TitleRules, EP_TAGS},
     rss::parse_mikan,
     VIDEO_EXTS,
 };
@@ -20,10 +22,16 @@ use crate::{
 pub async fn check_mikan(
     m: &Mikan,
     dl_client: &mut dyn Client,
-    dl_server_torrents: &[Torrent],
+    dl_server_torrents: &ServerTorrents,
     added_torrent_hashs: &mut Vec<String>,
     maybe_link: &Option<Link>,
 ) -> Result<()> {
+    let title_rules = TitleRules::new(
+        m.episode_regex.as_deref(),
+        m.season_regex.as_deref(),
+        &m.title_rewrite,
+    )?;
+    let tag_filters = compile_tag_filter(&m.tag_filter)?;
     let mut ts = parse_mikan(&m.url)
         .await?
         .into_iter()
@@ -38,12 +46,18 @@ pub async fn check_mikan(
                 }
             }) && m.title_contain.iter().all(|s| title.contains(s))
         })
-        .collect::<Vec<(String, String, Bytes)>>();
+        .map(|(title, _, bytes)| {
+            let parsed = title_rules.process(&title);
+            (title, bytes, parsed)
+        })
+        .filter(|(_, _, parsed)| match_tag_filter(parsed, &tag_filters))
+        .collect::<Vec<(String, Bytes, Result<Episode>)>>();
     for e in &m.extra {
-        ts.push((e.title.clone(), e.url.clone(), get_url_bytes(&e.url).await?));
+        let parsed = title_rules.process(&e.title);
+        ts.push((e.title.clone(), get_url_bytes(&e.url).await?, parsed));
     }
 
-    for (title, _, bytes) in ts {
+    for (title, bytes, parsed) in ts {
         if title.contains("合集") {
             // println!("跳过合集: {} ", title);
             continue;
@@ -52,11 +66,11 @@ pub async fn check_mikan(
         let torrent = lava_torrent::torrent::v1::Torrent::read_from_bytes(&bytes)?;
         let pathbuf_torrent_name = PathBuf::from(&torrent.name);
         // If the torrent contains only 1 file then files is None.
-        let (file_name_from_torrent, file_stem, storage_path) = if torrent.files.is_some() {
+        let (file_name_from_torrent, file_stem, storage_path) = if let Some(files) = &torrent.files
+        {
             let mut some_file_name_from_torrent = None;
             let mut is_multi_video_files = false;
-            // files is Some checked
-            for file in torrent.files.as_ref().unwrap() {
+            for file in files {
                 let file_suffix = file
                     .path
                     .extension()
@@ -107,25 +121,28 @@ pub async fn check_mikan(
             )
         };
 
-        let some_server_torrent = dl_server_torrents
-            .iter()
-            .find(|t| t.hash == torrent.info_hash());
-        if let Some(server_torrent) = some_server_torrent {
+        let source = Source::new(Kind::Mikan, &m.name);
+        if let Some(server_torrent) = dl_server_torrents.find(&torrent.info_hash()) {
             if let Some(link_config) = maybe_link {
                 if link_config.enable && (server_torrent.percent_done >= 1.0 || link_config.dry_run)
                 {
                     // If the torrent contains only 1 file then name is the file name. Otherwise it’s the suggested root directory’s name.
                     // let file_name_from_torrent = &torrent.name;
                     let file_suffix =
-                        file_name_from_torrent.split('.').last().ok_or_else(|| {
+                        file_name_from_torrent.rsplit('.').next().ok_or_else(|| {
                             eyre!("get file_suffix failed: {:?}", file_name_from_torrent)
                         })?;
-                    let parse_ep_result = process(&title, m);
-                    if parse_ep_result.is_err() {
-                        println!("解析'{title}'失败: {}", parse_ep_result.unwrap_err());
+                    let mut ep = match process(parsed, m) {
+                        Ok(ep) => ep,
+                        Err(e) => {
+                            println!("解析'{title}'失败: {e}");
+                            continue;
+                        }
+                    };
+                    if ep.confidence() < link_config.min_confidence {
+                        println!("解析'{title}'的可信度过低: {}", ep.confidence());
                         continue;
                     }
-                    let mut ep = parse_ep_result.unwrap();
 
                     // if season specified in config, use it to override the season parsed from title
                     if let Some(season) = m.season {
@@ -134,7 +151,10 @@ pub async fn check_mikan(
 
                     let name = ep.name(Some(&m.name))?;
                     let path = ep.link_path(&name);
-                    let link_file_name = ep.link_file_name(&name);
+                    let link_file_name = match &link_config.file_name_template {
+                        Some(template) => ep.link_file_name_by_template(template, &name),
+                        None => ep.link_file_name(&name),
+                    };
 
                     let full_path = format!("{}/{path}", &link_config.path);
                     let full_file_name = format!("{link_file_name}.{file_suffix}");
@@ -143,11 +163,21 @@ pub async fn check_mikan(
                         if link_config.dry_run {
                             println!("准备链接{link} <- {storage_path}{file_name_from_torrent}");
                         } else {
-                            fs::create_dir_all(&full_path)?;
                             let original = format!(
                                 "{}/{storage_path}{file_name_from_torrent}",
                                 &server_torrent.download_dir,
                             );
+                            if !verify_before_link(
+                                &original,
+                                ep.crc32(),
+                                &link_file_name,
+                                link_config,
+                            )
+                            .await?
+                            {
+                                continue;
+                            }
+                            fs::create_dir_all(&full_path)?;
                             match fs::hard_link(&original, &link) {
                                 Ok(_) => {
                                     println!(
@@ -179,12 +209,16 @@ pub async fn check_mikan(
 
             continue;
         }
+        if dl_server_torrents.is_unmanaged(&torrent.info_hash()) {
+            println!("下载器中已有手动添加的种子, 跳过: {}", title);
+            continue;
+        }
         if added_torrent_hashs.contains(&torrent.info_hash()) {
             println!("{} 刚刚已经被加入下载了", title);
             continue;
         }
         dl_client
-            .torrent_add_by_meta(general_purpose::STANDARD.encode(bytes), &m.name)
+            .torrent_add_by_meta(general_purpose::STANDARD.encode(bytes), &source)
             .await?;
         added_torrent_hashs.push(torrent.info_hash());
         println!("加入下载列表: {}", title)
@@ -193,8 +227,34 @@ pub async fn check_mikan(
     Ok(())
 }
 
-fn process(title: &str, m: &Mikan) -> Result<Episode> {
-    let mut ep = parser::process(title)?;
+pub(super) fn compile_tag_filter(tag_filter: &[TagFilter]) -> Result<Vec<(&str, Regex)>> {
+    tag_filter
+        .iter()
+        .map(|f| {
+            if !EP_TAGS.contains(&f.tag.as_str()) {
+                bail!("未知的tag_filter字段: {}, 可用的字段: {:?}", f.tag, EP_TAGS)
+            }
+            Ok((f.tag.as_str(), Regex::new(&f.pattern)?))
+        })
+        .collect()
+}
+
+/// 所有字段都匹配时返回true, 解析失败或者没有解析到字段时视为不匹配
+fn match_tag_filter(parsed: &Result<Episode>, tag_filters: &[(&str, Regex)]) -> bool {
+    if tag_filters.is_empty() {
+        return true;
+    }
+    let ep = match parsed {
+        Ok(Episode::Ep(ep)) | Ok(Episode::Batch(parser::Batch { ep, .. })) => ep,
+        _ => return false,
+    };
+    tag_filters
+        .iter()
+        .all(|(tag, re)| ep.tag(tag).is_some_and(|v| re.is_match(&v)))
+}
+
+fn process(parsed: Result<Episode>, m: &Mikan) -> Result<Episode> {
+    let mut ep = parsed?;
     ep.revise_ep(&m.ep_revise);
     Ok(ep)
 }
@@ -215,7 +275,59 @@ mod tests {
             "#,
         )
         .unwrap();
-        let ep = process("[Up to 21°C] 关于我转生变成史莱姆这档事 第三季 / Tensei shitara Slime Datta Ken 3rd Season - 49 (Baha 1920x1080 AVC AAC MP4)", &mikan);
+        let title_rules = TitleRules::new(None, None, &[]).unwrap();
+        let ep = process(title_rules.process("[Up to 21°C] 关于我转生变成史莱姆这档事 第三季 / Tensei shitara Slime Datta Ken 3rd Season - 49 (Baha 1920x1080 AVC AAC MP4)"), &mikan);
         assert!(matches!(ep, Ok(Episode::Ep(Ep {episode, ..})) if episode == 1 ))
     }
+
+    #[test]
+    fn test_process_with_title_rules() {
+        let mikan = toml::from_str::<Mikan>(
+            r#"
+            name = ""
+            url = ""
+            episode_regex = '#(\d+)'
+            season_regex = '(\d+)期'
+            title_rewrite = [{ pattern = '\s*\(先行版\)', replace = "" }]
+            "#,
+        )
+        .unwrap();
+        let title_rules = TitleRules::new(
+            mikan.episode_regex.as_deref(),
+            mikan.season_regex.as_deref(),
+            &mikan.title_rewrite,
+        )
+        .unwrap();
+        let ep = process(
+            title_rules.process("[某字幕组] 某番剧 2期 #07 (先行版) [1080p][简体]"),
+            &mikan,
+        )
+        .unwrap();
+        assert_eq!(ep.link_file_name("某番剧"), "某番剧 S02E7");
+        assert!(matches!(ep.name(None), Ok(name) if name == "某番剧"));
+    }
+
+    #[test]
+    fn test_tag_filter() {
+        let title_rules = TitleRules::new(None, None, &[]).unwrap();
+        let tag_filter = [TagFilter {
+            tag: "video_codec".to_string(),
+            pattern: "HEVC".to_string(),
+        }];
+        let tag_filters = compile_tag_filter(&tag_filter).unwrap();
+        assert!(match_tag_filter(
+            &title_rules.process("[LoliHouse] 迷宫饭 / Dungeon Meshi - 05 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]"),
+            &tag_filters
+        ));
+        assert!(!match_tag_filter(
+            &title_rules
+                .process("[ANi] 葬送的芙莉蓮 - 01 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]"),
+            &tag_filters
+        ));
+        assert!(compile_tag_filter(&[TagFilter {
+            tag: "codec".to_string(),
+            pattern: "HEVC".to_string(),
+        }])
+        .is_err());
+    }
 }
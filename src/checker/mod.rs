@@ -1,15 +1,29 @@
 mod collection;
+mod extras;
 mod mikan;
 mod res_rule;
+mod seeding;
+mod subtitle_pack;
+mod trackers;
+mod validate;
+mod verify;
 
 use std::sync::Mutex;
 
-pub use collection::check_collection;
+pub use collection::{
+    check_collection, infer_season_folders, preview_collection, CollectionPreview,
+};
 pub use mikan::check_mikan;
 pub use res_rule::check_res_rule;
+pub use seeding::check_seeding;
+pub use subtitle_pack::check_subtitle_pack;
+pub use trackers::check_trackers;
+pub use validate::{
+    validate, validate_collection, validate_mikan, validate_subtitle_pack, Issue, Level, Report,
+};
 
 use crate::{
-    config::{Collection, Config, Link, Mikan, Rule},
+    config::{Collection, Config, Downloader, Link, Mikan, Rule, SubtitlePack, CONFIG_STORE},
     dl::{self, Client},
 };
 use color_eyre::eyre::{eyre, Result};
@@ -22,11 +36,14 @@ pub async fn check_everything() -> Result<()> {
 }
 
 pub async fn check(collection: bool, mikan: bool, res: bool) -> Result<()> {
-    let config = Config::load()?;
+    let (config, _) = CONFIG_STORE.get()?;
     let result = check_with_config(&config, collection, mikan, res).await;
     if let Err(e) = &result {
         error!("{:?}", e);
     }
+    if let Err(e) = verify::save_crc32_cache() {
+        error!("保存CRC32缓存失败: {e}");
+    }
     if let Ok(mut last_check_result) = LAST_CHECK_RESULT.lock() {
         *last_check_result = Some(match &result {
             Ok(_) => Ok(()),
@@ -42,24 +59,16 @@ async fn check_with_config(
     mikan: bool,
     res: bool,
 ) -> Result<()> {
-    let mut dl_client = dl::get_client(&config.downloader);
-    let dl_server_torrents = dl_client.torrent_get().await?;
+    let mut downloaders = Downloaders::new(config);
     let mut added_torrent_hashs = Vec::new();
 
     if res {
-        check_res_rules(
-            &mut dl_client,
-            &dl_server_torrents,
-            &mut added_torrent_hashs,
-            &config.rules,
-        )
-        .await?;
+        check_res_rules(&mut downloaders, &mut added_torrent_hashs, &config.rules).await?;
     }
 
     if mikan {
         check_mikan_rss(
-            &mut dl_client,
-            &dl_server_torrents,
+            &mut downloaders,
             &mut added_torrent_hashs,
             &config.mikan,
             &config.link,
@@ -69,8 +78,7 @@ async fn check_with_config(
 
     if collection {
         check_collections(
-            &mut dl_client,
-            &dl_server_torrents,
+            &mut downloaders,
             &mut added_torrent_hashs,
             &config.collections,
             &config.link,
@@ -78,31 +86,128 @@ async fn check_with_config(
         .await?;
     }
 
+    // 字幕包需要和 mikan 或 collection 的视频配对
+    if mikan || collection {
+        check_subtitle_packs(
+            &mut downloaders,
+            &mut added_torrent_hashs,
+            &config.subtitle_packs,
+            &config.link,
+        )
+        .await?;
+    }
+
+    if config.trackers.is_some() || config.seeding.is_some() {
+        for name in distinct_downloaders(config) {
+            let (dl_client, dl_server_torrents) = downloaders.get(name).await?;
+            if let Some(trackers) = &config.trackers {
+                info!("checking trackers");
+                check_trackers(trackers, dl_client, &dl_server_torrents.managed).await?;
+            }
+            if let Some(seeding) = &config.seeding {
+                info!("checking seeding policy");
+                check_seeding(seeding, dl_client, &dl_server_torrents.managed).await?;
+            }
+        }
+    }
+
     Ok(())
 }
 
+/// 默认的和命名的下载器中指向不同 Transmission 的, 同一个 Transmission 只检查一次 trackers 和做种
+fn distinct_downloaders(config: &Config) -> Vec<Option<&str>> {
+    let mut seen = vec![];
+    let mut names = vec![];
+    let all = std::iter::once((None, &config.downloader)).chain(
+        config
+            .downloaders
+            .iter()
+            .map(|d| (Some(d.name.as_str()), &d.downloader)),
+    );
+    for (name, downloader) in all {
+        let key = downloader_key(downloader);
+        if !seen.contains(&key) {
+            seen.push(key);
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// url 和用户名相同的下载器是同一个 Transmission
+fn downloader_key(downloader: &Downloader) -> (&str, &str) {
+    let Downloader::Transmission(t) = downloader;
+    (t.url.trim_end_matches('/'), t.user.as_str())
+}
+
+/**
+ * 按名字路由的下载器, 每个下载器在第一次用到时创建, 并且只获取一次种子列表
+ * 指向同一个 Transmission 的下载器共用一个连接和种子列表
+ */
+struct Downloaders<'a> {
+    config: &'a Config,
+    clients: Vec<ConnectedDownloader<'a>>,
+}
+
+struct ConnectedDownloader<'a> {
+    key: (&'a str, &'a str),
+    dl_client: Box<dyn Client>,
+    dl_server_torrents: dl::ServerTorrents,
+}
+
+impl<'a> Downloaders<'a> {
+    fn new(config: &'a Config) -> Self {
+        Downloaders {
+            config,
+            clients: vec![],
+        }
+    }
+
+    /// name 为 None 时是默认的下载器
+    async fn get(&mut self, name: Option<&str>) -> Result<(&mut dyn Client, &dl::ServerTorrents)> {
+        let downloader = self.config.get_downloader(name)?;
+        let key = downloader_key(downloader);
+        let position = self.clients.iter().position(|c| c.key == key);
+        let index = match position {
+            Some(index) => index,
+            None => {
+                let mut dl_client = dl::get_client(downloader);
+                let dl_server_torrents = dl_client.torrent_get().await?;
+                self.clients.push(ConnectedDownloader {
+                    key,
+                    dl_client,
+                    dl_server_torrents,
+                });
+                self.clients.len() - 1
+            }
+        };
+        let connected = &mut self.clients[index];
+        Ok((connected.dl_client.as_mut(), &connected.dl_server_torrents))
+    }
+}
+
 async fn check_res_rules(
-    dl_client: &mut dyn Client,
-    dl_server_torrents: &[dl::Torrent],
+    downloaders: &mut Downloaders<'_>,
     added_torrent_hashs: &mut Vec<String>,
     rules: &[Rule],
 ) -> Result<()> {
     info!("{} rules to be checked", rules.len());
     for rule in rules.iter() {
+        let (dl_client, dl_server_torrents) = downloaders.get(rule.downloader.as_deref()).await?;
         check_res_rule(rule, dl_client, dl_server_torrents, added_torrent_hashs).await?;
     }
     Ok(())
 }
 
 async fn check_mikan_rss(
-    dl_client: &mut dyn Client,
-    dl_server_torrents: &[dl::Torrent],
+    downloaders: &mut Downloaders<'_>,
     added_torrent_hashs: &mut Vec<String>,
     mikan: &[Mikan],
     maybe_link: &Option<Link>,
 ) -> Result<()> {
     info!("{} mikan rss to be checked", mikan.len());
     for m in mikan {
+        let (dl_client, dl_server_torrents) = downloaders.get(m.downloader.as_deref()).await?;
         check_mikan(
             m,
             dl_client,
@@ -121,14 +226,15 @@ async fn check_mikan_rss(
 }
 
 async fn check_collections(
-    dl_client: &mut dyn Client,
-    dl_server_torrents: &[dl::Torrent],
+    downloaders: &mut Downloaders<'_>,
     added_torrent_hashs: &mut Vec<String>,
     collections: &[Collection],
     maybe_link: &Option<Link>,
 ) -> Result<()> {
     info!("{} collection to be checked", collections.len());
     for collection in collections {
+        let (dl_client, dl_server_torrents) =
+            downloaders.get(collection.downloader.as_deref()).await?;
         check_collection(
             collection,
             dl_client,
@@ -145,3 +251,76 @@ async fn check_collections(
 
     Ok(())
 }
+
+async fn check_subtitle_packs(
+    downloaders: &mut Downloaders<'_>,
+    added_torrent_hashs: &mut Vec<String>,
+    subtitle_packs: &[SubtitlePack],
+    maybe_link: &Option<Link>,
+) -> Result<()> {
+    info!("{} subtitle packs to be checked", subtitle_packs.len());
+    for pack in subtitle_packs {
+        let (dl_client, dl_server_torrents) = downloaders.get(pack.downloader.as_deref()).await?;
+        check_subtitle_pack(
+            pack,
+            dl_client,
+            dl_server_torrents,
+            added_torrent_hashs,
+            maybe_link,
+        )
+        .await?;
+    }
+
+    if !subtitle_packs.is_empty() {
+        info!("done checking subtitle packs")
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_downloaders() {
+        let config = Config::parse(
+            r#"
+            check_interval = 10
+            res_api = "dmhy"
+
+            [downloader]
+            type = "transmission"
+            url = "http://localhost:9091/transmission/rpc"
+            user = "admin"
+            password = "123123"
+
+            [[downloaders]]
+            name = "same"
+            type = "transmission"
+            url = "http://localhost:9091/transmission/rpc/"
+            user = "admin"
+            password = "123123"
+
+            [[downloaders]]
+            name = "nas"
+            type = "transmission"
+            url = "http://nas:9091/transmission/rpc"
+            user = "admin"
+            password = "123123"
+
+            [[downloaders]]
+            name = "nas-guest"
+            type = "transmission"
+            url = "http://nas:9091/transmission/rpc"
+            user = "guest"
+            password = "123123"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            distinct_downloaders(&config),
+            vec![None, Some("nas"), Some("nas-guest")]
+        );
+    }
+}
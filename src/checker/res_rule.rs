@@ -2,14 +2,14 @@ use color_eyre::eyre::Result;
 
 use crate::{
     config::Rule,
-    dl::{Client, Torrent},
+    dl::{Client, Kind, ServerTorrents, Source},
     res::{self, ApiServer},
 };
 
 pub async fn check_res_rule(
     rule: &Rule,
     dl_client: &mut dyn Client,
-    dl_server_torrents: &[Torrent],
+    dl_server_torrents: &ServerTorrents,
     added_torrent_hashs: &mut Vec<String>,
 ) -> Result<()> {
     let res_api = res::get_res_api(&rule.res_api);
@@ -21,15 +21,17 @@ pub async fn check_res_rule(
             rule.publish_after,
         )
         .await?;
+    let source = Source::new(Kind::Rule, &rule.name);
     for res in res_list {
-        if dl_server_torrents.iter().any(|t| res.info_hash == t.hash)
+        if dl_server_torrents.find(&res.info_hash).is_some()
+            || dl_server_torrents.is_unmanaged(&res.info_hash)
             || added_torrent_hashs.contains(&res.info_hash)
         {
             // println!("{} already in download server", res.title);
             continue;
         }
         dl_client
-            .torrent_add(res.magnet.to_string(), &rule.name)
+            .torrent_add(res.magnet.to_string(), &source)
             .await?;
         added_torrent_hashs.push(res.info_hash);
         println!("加入下载列表: {}", res.title)
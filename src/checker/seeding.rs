@@ -0,0 +1,357 @@
+use std::path::Path;
+
+use color_eyre::eyre::Result;
+
+use crate::{
+    config::Seeding,
+    dl::{Client, Torrent},
+    VIDEO_EXTS,
+};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, PartialEq)]
+enum SeedingAction {
+    Keep,
+    Pause,
+    Remove,
+}
+
+/// 按做种策略暂停或删除 muuf 添加的种子
+pub async fn check_seeding(
+    seeding: &Seeding,
+    dl_client: &mut dyn Client,
+    dl_server_torrents: &[Torrent],
+) -> Result<()> {
+    let mut to_pause = vec![];
+    let mut to_remove = vec![];
+    for torrent in dl_server_torrents {
+        match seeding_action(torrent, seeding, || is_linked(torrent)) {
+            SeedingAction::Keep => {}
+            SeedingAction::Pause => to_pause.push(torrent),
+            SeedingAction::Remove => to_remove.push(torrent),
+        }
+    }
+
+    if !to_pause.is_empty() {
+        dl_client.torrent_pause(&to_pause).await?;
+        for torrent in to_pause {
+            println!(
+                "停止做种: {} 分享率{:.2} 上传{}MB",
+                torrent.name,
+                torrent.upload_ratio,
+                torrent.uploaded / 1024 / 1024
+            );
+        }
+    }
+    let (with_data, without_data): (Vec<_>, Vec<_>) = to_remove
+        .into_iter()
+        .partition(|t| should_delete_data(t, seeding));
+    for (to_remove, delete_data) in [(with_data, true), (without_data, false)] {
+        if to_remove.is_empty() {
+            continue;
+        }
+        dl_client.torrent_remove(&to_remove, delete_data).await?;
+        for torrent in to_remove {
+            println!(
+                "删除种子{}: {} 分享率{:.2} 上传{}MB",
+                if delete_data { "和文件" } else { "" },
+                torrent.name,
+                torrent.upload_ratio,
+                torrent.uploaded / 1024 / 1024
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * 只处理下载完成的 muuf 添加的种子
+ * 达到 seed_ratio 或 seed_days 后, 开启 remove_after_link 且已经链接过的删除, 其他的暂停
+ * 都不设置时不限制做种, 开启 remove_after_link 时链接后直接删除
+ */
+fn seeding_action(
+    torrent: &Torrent,
+    seeding: &Seeding,
+    is_linked: impl FnOnce() -> bool,
+) -> SeedingAction {
+    if !torrent.is_managed() || torrent.percent_done < 1.0 {
+        return SeedingAction::Keep;
+    }
+    let done = match (seeding.seed_ratio, seeding.seed_days) {
+        (None, None) if seeding.remove_after_link => {
+            return match is_linked() {
+                true => SeedingAction::Remove,
+                false => SeedingAction::Keep,
+            };
+        }
+        (ratio, days) => {
+            ratio.is_some_and(|r| torrent.upload_ratio >= r)
+                || days.is_some_and(|d| torrent.seconds_seeding >= d as i64 * SECONDS_PER_DAY)
+        }
+    };
+    if !done {
+        SeedingAction::Keep
+    } else if seeding.remove_after_link && is_linked() {
+        SeedingAction::Remove
+    } else if torrent.paused {
+        SeedingAction::Keep
+    } else {
+        SeedingAction::Pause
+    }
+}
+
+/// 只按下载目录判断的旧种子需要开启 delete_unlabelled_data 才删除文件
+fn should_delete_data(torrent: &Torrent, seeding: &Seeding) -> bool {
+    seeding.delete_data && (torrent.is_labelled() || seeding.delete_unlabelled_data)
+}
+
+/**
+ * 种子中所有需要下载的视频文件的硬链接数都大于1时, 认为已经链接过
+ * 只链接了部分集数的种子删除数据时会删掉没有链接的文件, 不能算作链接过
+ */
+fn is_linked(torrent: &Torrent) -> bool {
+    let mut videos = torrent
+        .files
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| torrent.wanted.get(*i).copied().unwrap_or(true))
+        .map(|(_, f)| f)
+        .filter(|f| {
+            f.rsplit_once('.')
+                .is_some_and(|(_, ext)| VIDEO_EXTS.contains(&ext.to_lowercase().as_str()))
+        })
+        .peekable();
+    videos.peek().is_some()
+        && videos.all(|f| hard_link_count(&Path::new(&torrent.download_dir).join(f)) > 1)
+}
+
+#[cfg(unix)]
+fn hard_link_count(path: &Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    path.metadata().map_or(0, |m| m.nlink())
+}
+
+#[cfg(not(unix))]
+fn hard_link_count(_path: &Path) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dl;
+
+    fn torrent(upload_ratio: f32, seed_days: i64, paused: bool) -> Torrent {
+        Torrent {
+            percent_done: 1.0,
+            paused,
+            upload_ratio,
+            seconds_seeding: seed_days * SECONDS_PER_DAY,
+            ..Torrent::fixture("n")
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_linked() {
+        use std::fs;
+        let dir = std::env::temp_dir().join(format!("muuf-seeding-{}", std::process::id()));
+        let links = dir.join("links");
+        fs::create_dir_all(&links).unwrap();
+        let mut torrent = Torrent {
+            download_dir: dir.to_string_lossy().to_string(),
+            files: vec![
+                "E01.mkv".to_string(),
+                "E02.mkv".to_string(),
+                "E03.mkv".to_string(),
+                "cover.jpg".to_string(),
+            ],
+            wanted: vec![true, true, false, true],
+            ..Torrent::fixture("n")
+        };
+        for file in ["E01.mkv", "E02.mkv", "cover.jpg"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        fs::hard_link(dir.join("E01.mkv"), links.join("E01.mkv")).unwrap();
+        // 只链接了一部分
+        assert!(!is_linked(&torrent));
+        // 不下载的文件和不是视频的文件不需要链接
+        fs::hard_link(dir.join("E02.mkv"), links.join("E02.mkv")).unwrap();
+        assert!(is_linked(&torrent));
+        torrent.wanted[2] = true;
+        assert!(!is_linked(&torrent));
+        // 没有视频文件
+        torrent.files = vec!["cover.jpg".to_string()];
+        assert!(!is_linked(&torrent));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn seeding(seed_ratio: Option<f32>, seed_days: Option<u32>, remove: bool) -> Seeding {
+        Seeding {
+            seed_ratio,
+            seed_days,
+            remove_after_link: remove,
+            delete_data: false,
+            delete_unlabelled_data: false,
+        }
+    }
+
+    /// 记录暂停和删除的种子
+    #[derive(Default)]
+    struct Recorder {
+        paused: Vec<String>,
+        /// (hash, delete_data)
+        removed: Vec<(String, bool)>,
+    }
+
+    #[async_trait::async_trait]
+    impl Client for Recorder {
+        async fn torrent_add(&mut self, _: String, _: &dl::Source) -> Result<()> {
+            unreachable!()
+        }
+        async fn torrent_add_by_meta_with_files(
+            &mut self,
+            _: String,
+            _: &dl::Source,
+            _: &[usize],
+        ) -> Result<()> {
+            unreachable!()
+        }
+        async fn torrent_set_files_wanted(
+            &mut self,
+            _: &Torrent,
+            _: &[usize],
+            _: &[usize],
+        ) -> Result<()> {
+            unreachable!()
+        }
+        async fn torrent_set_tracker_list(&mut self, _: &[&Torrent], _: Vec<String>) -> Result<()> {
+            unreachable!()
+        }
+        async fn torrent_get(&mut self) -> Result<dl::ServerTorrents> {
+            unreachable!()
+        }
+        async fn torrent_pause(&mut self, torrents: &[&Torrent]) -> Result<()> {
+            self.paused.extend(torrents.iter().map(|t| t.hash.clone()));
+            Ok(())
+        }
+        async fn torrent_remove(&mut self, torrents: &[&Torrent], delete_data: bool) -> Result<()> {
+            self.removed
+                .extend(torrents.iter().map(|t| (t.hash.clone(), delete_data)));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_seeding_skips_unmanaged() {
+        let policy = Seeding {
+            delete_data: true,
+            ..seeding(Some(1.0), None, false)
+        };
+        // 手动添加的和订阅中的是同一个种子
+        let manual = Torrent {
+            hash: "same".to_string(),
+            download_dir: "/downloads/".to_string(),
+            ..torrent(5.0, 30, false)
+        };
+        let server_torrents = dl::ServerTorrents::new(vec![manual]);
+        assert!(server_torrents.is_unmanaged("same"));
+        let mut recorder = Recorder::default();
+        check_seeding(&policy, &mut recorder, &server_torrents.managed)
+            .await
+            .unwrap();
+        // 即使直接传入也不会处理
+        let manual = Torrent {
+            hash: "same".to_string(),
+            download_dir: "/downloads/".to_string(),
+            ..torrent(5.0, 30, false)
+        };
+        check_seeding(&policy, &mut recorder, &[manual])
+            .await
+            .unwrap();
+        assert!(recorder.paused.is_empty());
+        assert!(recorder.removed.is_empty());
+    }
+
+    #[test]
+    fn test_should_delete_data() {
+        let labelled = Torrent {
+            labels: vec!["muuf".to_string()],
+            ..torrent(2.0, 1, false)
+        };
+        let legacy = torrent(2.0, 1, false);
+        let mut policy = seeding(Some(1.0), None, true);
+        assert!(!should_delete_data(&labelled, &policy));
+        policy.delete_data = true;
+        assert!(should_delete_data(&labelled, &policy));
+        // 没有标签的默认不删除文件
+        assert!(!should_delete_data(&legacy, &policy));
+        policy.delete_unlabelled_data = true;
+        assert!(should_delete_data(&legacy, &policy));
+    }
+
+    #[test]
+    fn test_seeding_action() {
+        let policy = seeding(Some(2.0), Some(7), false);
+        assert_eq!(
+            seeding_action(&torrent(1.0, 1, false), &policy, || true),
+            SeedingAction::Keep
+        );
+        assert_eq!(
+            seeding_action(&torrent(2.0, 1, false), &policy, || true),
+            SeedingAction::Pause
+        );
+        assert_eq!(
+            seeding_action(&torrent(1.0, 7, false), &policy, || true),
+            SeedingAction::Pause
+        );
+        assert_eq!(
+            seeding_action(&torrent(2.0, 1, true), &policy, || true),
+            SeedingAction::Keep
+        );
+
+        // 没有链接过的不删除
+        let policy = seeding(Some(2.0), None, true);
+        assert_eq!(
+            seeding_action(&torrent(2.0, 1, false), &policy, || true),
+            SeedingAction::Remove
+        );
+        assert_eq!(
+            seeding_action(&torrent(2.0, 1, false), &policy, || false),
+            SeedingAction::Pause
+        );
+
+        // 不限制做种
+        let policy = seeding(None, None, false);
+        assert_eq!(
+            seeding_action(&torrent(9.0, 99, false), &policy, || true),
+            SeedingAction::Keep
+        );
+        let policy = seeding(None, None, true);
+        assert_eq!(
+            seeding_action(&torrent(0.0, 0, false), &policy, || true),
+            SeedingAction::Remove
+        );
+        assert_eq!(
+            seeding_action(&torrent(0.0, 0, false), &policy, || false),
+            SeedingAction::Keep
+        );
+
+        // 不是 muuf 添加的, 或者没有下载完成
+        let policy = seeding(Some(0.0), None, true);
+        let mut other = torrent(2.0, 1, false);
+        other.download_dir = "/downloads/other".to_string();
+        assert_eq!(
+            seeding_action(&other, &policy, || true),
+            SeedingAction::Keep
+        );
+        let mut downloading = torrent(2.0, 1, false);
+        downloading.percent_done = 0.5;
+        assert_eq!(
+            seeding_action(&downloading, &policy, || true),
+            SeedingAction::Keep
+        );
+    }
+}
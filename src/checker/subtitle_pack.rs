@@ -0,0 +1,216 @@
+use std::{ffi::OsStr, fs, path::Path};
+
+use base64::{engine::general_purpose, Engine};
+use color_eyre::eyre::{eyre, Result};
+use lava_torrent::torrent::v1::Torrent;
+use regex::Regex;
+
+use crate::{
+    config::{Link, SubtitlePack},
+    dl::{self, Client, Kind, ServerTorrents, Source},
+    get_url_bytes,
+    parser::{self, Episode, TitleRules, FONT_EXTS, SUBTITLE_EXTS},
+    VIDEO_EXTS,
+};
+
+const ARCHIVE_EXTS: [&str; 3] = ["zip", "7z", "rar"];
+
+pub async fn check_subtitle_pack(
+    pack: &SubtitlePack,
+    dl_client: &mut dyn Client,
+    dl_server_torrents: &ServerTorrents,
+    added_torrent_hashs: &mut Vec<String>,
+    maybe_link: &Option<Link>,
+) -> Result<()> {
+    let bytes = get_url_bytes(&pack.torrent_url).await?;
+    let torrent = Torrent::read_from_bytes(&bytes)?;
+
+    let source = Source::new(Kind::SubtitlePack, &pack.name);
+    if let Some(server_torrent) = dl_server_torrents.find(&torrent.info_hash()) {
+        if let Some(link_config) = maybe_link {
+            if link_config.enable && server_torrent.percent_done >= 1.0 {
+                link_subtitle_pack(pack, &torrent, link_config, server_torrent)?;
+            }
+        }
+
+        return Ok(());
+    }
+    if dl_server_torrents.is_unmanaged(&torrent.info_hash()) {
+        println!("下载器中已有手动添加的种子, 跳过: {}", pack.title);
+        return Ok(());
+    }
+    if added_torrent_hashs.contains(&torrent.info_hash()) {
+        println!("{} 刚刚已经被加入下载了", pack.title);
+        return Ok(());
+    }
+    dl_client
+        .torrent_add_by_meta(general_purpose::STANDARD.encode(bytes), &source)
+        .await?;
+    added_torrent_hashs.push(torrent.info_hash());
+    println!("加入下载列表: {}", pack.title);
+
+    Ok(())
+}
+
+fn link_subtitle_pack(
+    pack: &SubtitlePack,
+    torrent: &Torrent,
+    link_config: &Link,
+    server_torrent: &dl::Torrent,
+) -> Result<()> {
+    let title_rules = TitleRules::new(pack.episode_regex.as_deref(), None, &[])?;
+    // (种子中的文件名, 下载后的完整路径)
+    let files = match &torrent.files {
+        Some(files) => files
+            .iter()
+            .map(|file| {
+                let path = file
+                    .path
+                    .to_str()
+                    .ok_or_else(|| eyre!("get path & to_str failed: {:?}", file.path))?;
+                let file_name = file
+                    .path
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .ok_or_else(|| eyre!("get file_name & to_str failed: {:?}", file.path))?;
+                Ok((
+                    file_name,
+                    format!("{}/{}/{path}", &server_torrent.download_dir, &torrent.name),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        // If the torrent contains only 1 file then name is the file name.
+        None => vec![(
+            torrent.name.as_str(),
+            format!("{}/{}", &server_torrent.download_dir, &torrent.name),
+        )],
+    };
+
+    for (file_name, original) in files {
+        let Some(file_suffix) = file_name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_lowercase())
+        else {
+            continue;
+        };
+        if FONT_EXTS.contains(&file_suffix.as_str()) {
+            if link_config.collect_fonts {
+                let link = Path::new(&link_config.path)
+                    .join(&pack.name)
+                    .join("fonts")
+                    .join(file_name);
+                parser::link_file(&original, &link, link_config.dry_run, "字体")?;
+            }
+            continue;
+        }
+        if ARCHIVE_EXTS.contains(&file_suffix.as_str()) {
+            println!("跳过字幕包中的压缩包, 需要手动解压: {original}");
+            continue;
+        }
+        if !SUBTITLE_EXTS.contains(&file_suffix.as_str()) {
+            continue;
+        }
+
+        let stem = parser::subtitle_stem(file_name);
+        let mut ep = match title_rules.process(stem) {
+            Ok(ep @ Episode::Ep(_)) => ep,
+            Ok(_) => {
+                println!("{file_name} 不是单集字幕, 跳过");
+                continue;
+            }
+            Err(e) => {
+                println!("{file_name} 解析失败: {e}");
+                continue;
+            }
+        };
+        ep.revise_ep(&pack.ep_revise);
+        if let Some(season) = pack.season {
+            ep = ep.with_season(season);
+        }
+
+        let full_path = format!("{}/{}", &link_config.path, ep.link_path(&pack.name));
+        let link_file_name_re =
+            ep.link_file_name_regex(link_config.file_name_template.as_deref(), &pack.name);
+        let Some(video_stem) = find_linked_video(&full_path, &link_file_name_re)? else {
+            println!("还没有链接对应的视频, 跳过字幕: {file_name}");
+            continue;
+        };
+        let Some(track) = parser::subtitle_track(file_name, stem, link_config) else {
+            println!("无法判断字幕语言: {file_name}");
+            continue;
+        };
+        let link = Path::new(&full_path).join(track.link_file_name(&video_stem, &file_suffix));
+        parser::link_file(&original, &link, link_config.dry_run, "字幕")?;
+    }
+
+    Ok(())
+}
+
+/**
+ * 在链接目录中寻找已经链接好的视频, 返回不带扩展名的文件名
+ * 文件名用和链接时相同的 file_name_template 匹配, 见 [`Episode::link_file_name_regex`]
+ */
+fn find_linked_video(full_path: &str, link_file_name_re: &Regex) -> Result<Option<String>> {
+    let dir = Path::new(full_path);
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let (Some(stem), Some(ext)) = (
+            path.file_stem().and_then(OsStr::to_str),
+            path.extension().and_then(OsStr::to_str),
+        ) else {
+            continue;
+        };
+        if !VIDEO_EXTS.contains(&ext.to_lowercase().as_str()) {
+            continue;
+        }
+        if link_file_name_re.is_match(stem) {
+            return Ok(Some(stem.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_linked_video() {
+        let dir = std::env::temp_dir().join(format!("muuf-linked-video-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "孤独摇滚 S01E1 [1080p].mkv",
+            "孤独摇滚 S01E10.mkv",
+            "孤独摇滚 S01E2.zh.ass",
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let full_path = dir.to_str().unwrap();
+        let template = Some("{name} S{season}E{episode} [{resolution}]");
+        let re = |title: &str| {
+            parser::process(title)
+                .unwrap()
+                .link_file_name_regex(template, "孤独摇滚")
+        };
+        assert_eq!(
+            find_linked_video(full_path, &re("[LoliHouse] 孤独摇滚 - 01")).unwrap(),
+            Some("孤独摇滚 S01E1 [1080p]".to_string())
+        );
+        assert_eq!(
+            find_linked_video(full_path, &re("[LoliHouse] 孤独摇滚 - 10 [720p]")).unwrap(),
+            Some("孤独摇滚 S01E10".to_string())
+        );
+        assert_eq!(
+            find_linked_video(full_path, &re("[LoliHouse] 孤独摇滚 - 02")).unwrap(),
+            None
+        );
+        assert_eq!(
+            find_linked_video("/not/exist", &re("[LoliHouse] 孤独摇滚 - 01")).unwrap(),
+            None
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+use std::time::{Duration, SystemTime};
+
+use color_eyre::eyre::Result;
+use tokio::fs;
+use tracing::error;
+
+use crate::{
+    config::Trackers,
+    dl::{Client, Torrent},
+    get_data_dir, get_url_bytes,
+};
+
+const TRACKERS_CACHE_FILE_NAME: &str = "trackers.txt";
+const DEFAULT_REFRESH_HOURS: u64 = 24;
+
+/// 给 muuf 添加的种子追加配置中的tracker
+pub async fn check_trackers(
+    trackers: &Trackers,
+    dl_client: &mut dyn Client,
+    dl_server_torrents: &[Torrent],
+) -> Result<()> {
+    let extra_trackers = load_trackers(trackers).await;
+    if extra_trackers.is_empty() {
+        return Ok(());
+    }
+    for torrent in dl_server_torrents {
+        if let Some(tracker_list) = merge_trackers(torrent, &extra_trackers, trackers.max_peers) {
+            let added = tracker_list.len() - torrent.trackers.len();
+            dl_client
+                .torrent_set_tracker_list(&[torrent], tracker_list)
+                .await?;
+            println!("追加{added}个tracker: {}", torrent.name);
+        }
+    }
+    Ok(())
+}
+
+/**
+ * 合并 list file url 中的tracker, url 的内容缓存在数据目录中, 下载失败时使用缓存
+ * 文件读取或缓存写入失败时只打印错误, 使用其他来源的tracker, 不影响之后的做种策略
+ */
+async fn load_trackers(trackers: &Trackers) -> Vec<String> {
+    let mut content = trackers.list.join("\n");
+    if let Some(file) = &trackers.file {
+        match fs::read_to_string(file).await {
+            Ok(file_content) => {
+                content.push('\n');
+                content.push_str(&file_content);
+            }
+            Err(e) => error!("读取tracker文件失败 {file}: {e}"),
+        }
+    }
+    if let Some(url) = &trackers.url {
+        let cache = get_data_dir().join(TRACKERS_CACHE_FILE_NAME);
+        let refresh =
+            Duration::from_secs(trackers.refresh_hours.unwrap_or(DEFAULT_REFRESH_HOURS) * 60 * 60);
+        let expired = fs::metadata(&cache)
+            .await
+            .and_then(|m| m.modified())
+            .map_or(true, |modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .map_or(true, |age| age >= refresh)
+            });
+        let mut downloaded = None;
+        if expired {
+            match get_url_bytes(url).await {
+                Ok(bytes) => {
+                    if let Err(e) = fs::write(&cache, &bytes).await {
+                        error!("保存tracker列表缓存失败: {e}");
+                    }
+                    downloaded = Some(String::from_utf8_lossy(&bytes).to_string());
+                }
+                Err(e) => println!("下载tracker列表失败: {e}"),
+            }
+        }
+        let remote = match downloaded {
+            Some(downloaded) => Some(downloaded),
+            None => fs::read_to_string(&cache).await.ok(),
+        };
+        if let Some(remote) = remote {
+            content.push('\n');
+            content.push_str(&remote);
+        }
+    }
+    parse_trackers(&content)
+}
+
+/// 每行一个tracker, 忽略空行和#开头的行, 去重
+fn parse_trackers(content: &str) -> Vec<String> {
+    let mut trackers: Vec<String> = vec![];
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || trackers.iter().any(|t| t == line) {
+            continue;
+        }
+        trackers.push(line.to_string());
+    }
+    trackers
+}
+
+/// 需要追加时返回追加后完整的tracker列表
+fn merge_trackers(
+    torrent: &Torrent,
+    extra_trackers: &[String],
+    max_peers: Option<i64>,
+) -> Option<Vec<String>> {
+    if !torrent.is_managed() || torrent.paused {
+        return None;
+    }
+    if max_peers.is_some_and(|max| torrent.peers_connected >= max) {
+        return None;
+    }
+    let missing = extra_trackers
+        .iter()
+        .filter(|t| !torrent.trackers.contains(t))
+        .cloned()
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return None;
+    }
+    Some(torrent.trackers.iter().cloned().chain(missing).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torrent(trackers: &[&str], peers_connected: i64) -> Torrent {
+        Torrent {
+            percent_done: 0.5,
+            trackers: trackers.iter().map(|t| t.to_string()).collect(),
+            peers_connected,
+            ..Torrent::fixture("n")
+        }
+    }
+
+    #[test]
+    fn test_parse_trackers() {
+        assert_eq!(
+            parse_trackers(
+                "udp://a:1337/announce\n\n# comment\n  http://b/announce  \nudp://a:1337/announce\n"
+            ),
+            vec!["udp://a:1337/announce", "http://b/announce"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_trackers_missing_file() {
+        let trackers = Trackers {
+            list: vec!["udp://a:1337/announce".to_string()],
+            file: Some("/not/exist/trackers.txt".to_string()),
+            url: None,
+            refresh_hours: None,
+            max_peers: None,
+        };
+        assert_eq!(
+            load_trackers(&trackers).await,
+            vec!["udp://a:1337/announce"]
+        );
+    }
+
+    #[test]
+    fn test_merge_trackers() {
+        let extra = vec!["udp://a".to_string(), "udp://b".to_string()];
+        assert_eq!(
+            merge_trackers(&torrent(&["udp://a"], 0), &extra, None),
+            Some(vec!["udp://a".to_string(), "udp://b".to_string()])
+        );
+        assert_eq!(
+            merge_trackers(&torrent(&["udp://b", "udp://a"], 0), &extra, None),
+            None
+        );
+        assert_eq!(merge_trackers(&torrent(&[], 10), &extra, Some(5)), None);
+        assert_eq!(
+            merge_trackers(&torrent(&[], 4), &extra, Some(5)),
+            Some(extra.clone())
+        );
+
+        let mut other = torrent(&[], 0);
+        other.download_dir = "/downloads/other".to_string();
+        assert_eq!(merge_trackers(&other, &extra, None), None);
+    }
+}
@@ -0,0 +1,510 @@
+/*
+   muuf validate 的语义检查, 反序列化成功之后配置仍可能在检查时出错
+   error 会导致检查或链接失败, warning 可能不是想要的结果
+   --online 时再检查下载器和订阅的链接能否访问
+*/
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use futures::future::join_all;
+use regex::Regex;
+use reqwest::Url;
+use serde::Serialize;
+
+use super::{collection::glob_set, mikan::compile_tag_filter};
+use crate::{
+    config::{Collection, Config, Downloader, Mikan, SubtitlePack},
+    dl::{self, DOWNLOAD_DIR},
+    CLIENT,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    pub level: Level,
+    /// 如 mikan[孤独摇滚].episode_regex
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub valid: bool,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Level::Error, path.into(), message.into());
+    }
+
+    fn warning(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Level::Warning, path.into(), message.into());
+    }
+
+    fn push(&mut self, level: Level, path: String, message: String) {
+        match level {
+            Level::Error => self.errors += 1,
+            Level::Warning => self.warnings += 1,
+        }
+        self.valid = self.errors == 0;
+        self.issues.push(Issue {
+            level,
+            path,
+            message,
+        });
+    }
+
+    /// 配置文件读取失败时只有一个错误
+    pub fn load_failed(message: String) -> Report {
+        let mut report = Report::default();
+        report.error("", message);
+        report
+    }
+}
+
+pub async fn validate(config: &Config, online: bool) -> Report {
+    let mut report = Report {
+        valid: true,
+        ..Default::default()
+    };
+    check_regexes(config, &mut report);
+    check_downloaders(config, &mut report);
+    check_link(config, &mut report);
+    check_duplicates(config, &mut report);
+    check_ep_revise(config, &mut report);
+    if online {
+        // 代理或下载器配置有错误时 HTTP 客户端和下载器无法创建
+        match report.valid {
+            true => check_online(config, &mut report).await,
+            false => report.warning("", "配置有错误, 没有进行连接检查"),
+        }
+    }
+    report
+}
+
+fn regex(report: &mut Report, path: String, pattern: &str) -> Option<Regex> {
+    match Regex::new(pattern) {
+        Ok(re) => Some(re),
+        Err(e) => {
+            report.error(path, format!("正则格式错误: {e}"));
+            None
+        }
+    }
+}
+
+/// 自定义的 season_regex 和 episode_regex 从捕获组中取数字
+fn capture_regex(report: &mut Report, path: String, pattern: &Option<String>) {
+    let Some(pattern) = pattern else {
+        return;
+    };
+    if let Some(re) = regex(report, path.clone(), pattern) {
+        if re.captures_len() < 2 {
+            report.error(path, "没有捕获组, 不能从中取到数字");
+        }
+    }
+}
+
+fn check_regexes(config: &Config, report: &mut Report) {
+    for m in &config.mikan {
+        check_mikan(report, &format!("mikan[{}]", m.name), m);
+    }
+    for c in &config.collections {
+        check_collection(report, &format!("collections[{}]", c.name), c);
+    }
+    for p in &config.subtitle_packs {
+        check_subtitle_pack(report, &format!("subtitle_packs[{}]", p.name), p);
+    }
+}
+
+/// path 为空时是单独检查一个订阅, 只用字段名
+fn field(path: &str, name: &str) -> String {
+    match path {
+        "" => name.to_string(),
+        path => format!("{path}.{name}"),
+    }
+}
+
+fn check_mikan(report: &mut Report, path: &str, m: &Mikan) {
+    capture_regex(report, field(path, "episode_regex"), &m.episode_regex);
+    capture_regex(report, field(path, "season_regex"), &m.season_regex);
+    for (i, r) in m.title_rewrite.iter().enumerate() {
+        regex(
+            report,
+            field(path, &format!("title_rewrite[{i}]")),
+            &r.pattern,
+        );
+    }
+    if let Err(e) = compile_tag_filter(&m.tag_filter) {
+        report.error(field(path, "tag_filter"), e.to_string());
+    }
+}
+
+fn check_collection(report: &mut Report, path: &str, c: &Collection) {
+    capture_regex(report, field(path, "episode_regex"), &c.episode_regex);
+    capture_regex(report, field(path, "season_regex"), &c.season_regex);
+    for (i, r) in c.title_rewrite.iter().enumerate() {
+        regex(
+            report,
+            field(path, &format!("title_rewrite[{i}]")),
+            &r.pattern,
+        );
+    }
+    for (i, mapping) in c.special_mappings.iter().enumerate() {
+        if mapping.match_and_replace {
+            regex(
+                report,
+                field(path, &format!("special_mappings[{i}]")),
+                &mapping.file_name,
+            );
+        }
+    }
+    if let Err(e) = glob_set(&c.include) {
+        report.error(field(path, "include"), e.to_string());
+    }
+    if let Err(e) = glob_set(&c.exclude) {
+        report.error(field(path, "exclude"), e.to_string());
+    }
+}
+
+fn check_subtitle_pack(report: &mut Report, path: &str, p: &SubtitlePack) {
+    capture_regex(report, field(path, "episode_regex"), &p.episode_regex);
+}
+
+/// API 保存一个订阅前的检查, 和 validate 中对配置文件的检查相同
+pub fn validate_mikan(m: &Mikan) -> Report {
+    single(|report| check_mikan(report, "", m))
+}
+
+pub fn validate_collection(c: &Collection) -> Report {
+    single(|report| check_collection(report, "", c))
+}
+
+pub fn validate_subtitle_pack(p: &SubtitlePack) -> Report {
+    single(|report| check_subtitle_pack(report, "", p))
+}
+
+fn single(check: impl FnOnce(&mut Report)) -> Report {
+    let mut report = Report {
+        valid: true,
+        ..Default::default()
+    };
+    check(&mut report);
+    report
+}
+
+fn downloader_url(report: &mut Report, path: String, downloader: &Downloader) {
+    let Downloader::Transmission(t) = downloader;
+    match Url::parse(&t.url) {
+        Ok(url) if ["http", "https"].contains(&url.scheme()) => {}
+        Ok(url) => report.error(path, format!("不支持的协议 {}", url.scheme())),
+        Err(e) => report.error(path, format!("URL格式错误 {}: {e}", t.url)),
+    }
+}
+
+fn check_downloaders(config: &Config, report: &mut Report) {
+    downloader_url(report, "downloader.url".to_string(), &config.downloader);
+    let mut names = HashSet::new();
+    for d in &config.downloaders {
+        let path = format!("downloaders[{}]", d.name);
+        downloader_url(report, format!("{path}.url"), &d.downloader);
+        if !names.insert(d.name.as_str()) {
+            report.error(path, "下载器的名字重复");
+        }
+    }
+
+    let references = config
+        .rules
+        .iter()
+        .map(|r| (format!("rules[{}]", r.name), &r.downloader))
+        .chain(
+            config
+                .mikan
+                .iter()
+                .map(|m| (format!("mikan[{}]", m.name), &m.downloader)),
+        )
+        .chain(
+            config
+                .collections
+                .iter()
+                .map(|c| (format!("collections[{}]", c.name), &c.downloader)),
+        )
+        .chain(
+            config
+                .subtitle_packs
+                .iter()
+                .map(|p| (format!("subtitle_packs[{}]", p.name), &p.downloader)),
+        );
+    for (path, downloader) in references {
+        if let Some(name) = downloader {
+            if !names.contains(name.as_str()) {
+                report.error(
+                    format!("{path}.downloader"),
+                    format!("没有名为{name}的下载器"),
+                );
+            }
+        }
+    }
+
+    if let Some(proxy) = &config.proxy {
+        if let Err(e) = reqwest::Proxy::all(&proxy.scheme) {
+            report.error("proxy.scheme", format!("代理格式错误: {e}"));
+        }
+    }
+}
+
+/// 链接使用硬链接, 链接目录和下载目录必须在同一个文件系统中
+fn check_link(config: &Config, report: &mut Report) {
+    let Some(link) = &config.link else {
+        return;
+    };
+    if !link.enable {
+        return;
+    }
+    let path = Path::new(&link.path);
+    let Ok(metadata) = fs::metadata(path) else {
+        report.error("link.path", format!("{} 不存在", link.path));
+        return;
+    };
+    if !metadata.is_dir() {
+        report.error("link.path", format!("{} 不是文件夹", link.path));
+        return;
+    }
+    if link.dry_run {
+        return;
+    }
+    let Ok(download) = fs::metadata(DOWNLOAD_DIR) else {
+        report.warning(
+            "link.path",
+            format!("下载目录 {DOWNLOAD_DIR} 不存在, 无法检查是否和链接目录在同一个文件系统中"),
+        );
+        return;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if metadata.dev() != download.dev() {
+            report.error(
+                "link.path",
+                format!(
+                    "{} 和下载目录 {DOWNLOAD_DIR} 不在同一个文件系统中, 不能创建硬链接",
+                    link.path
+                ),
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = download;
+}
+
+fn duplicates<'a>(
+    report: &mut Report,
+    field: &str,
+    keys: impl Iterator<Item = (&'a str, &'a str)>,
+    what: &str,
+) {
+    let mut seen = HashMap::new();
+    for (name, key) in keys {
+        if let Some(first) = seen.insert(key, name) {
+            report.error(
+                format!("{field}[{name}]"),
+                format!("{what}和{field}[{first}]重复: {key}"),
+            );
+        }
+    }
+}
+
+fn check_duplicates(config: &Config, report: &mut Report) {
+    duplicates(
+        report,
+        "mikan",
+        config
+            .mikan
+            .iter()
+            .map(|m| (m.name.as_str(), m.url.as_str())),
+        "url",
+    );
+    duplicates(
+        report,
+        "collections",
+        config
+            .collections
+            .iter()
+            .map(|c| (c.name.as_str(), c.torrent_url.as_str())),
+        "torrent_url",
+    );
+    duplicates(
+        report,
+        "subtitle_packs",
+        config
+            .subtitle_packs
+            .iter()
+            .map(|p| (p.name.as_str(), p.torrent_url.as_str())),
+        "torrent_url",
+    );
+    duplicates(
+        report,
+        "rules",
+        config
+            .rules
+            .iter()
+            .map(|r| (r.name.as_str(), r.name.as_str())),
+        "名字",
+    );
+    // 不同季的 mikan 可以同名, 同名同季会链接到同样的文件名
+    let mut seen = HashMap::new();
+    for m in &config.mikan {
+        if m.season.is_some() && seen.insert((&m.name, m.season), &m.url).is_some() {
+            report.warning(
+                format!("mikan[{}].season", m.name),
+                format!(
+                    "有多个同名的 mikan 都是第{}季",
+                    m.season.unwrap_or_default()
+                ),
+            );
+        }
+    }
+}
+
+fn check_ep_revise(config: &Config, report: &mut Report) {
+    let revises = config
+        .mikan
+        .iter()
+        .map(|m| (format!("mikan[{}]", m.name), m.ep_revise))
+        .chain(
+            config
+                .subtitle_packs
+                .iter()
+                .map(|p| (format!("subtitle_packs[{}]", p.name), p.ep_revise)),
+        );
+    for (path, ep_revise) in revises {
+        if ep_revise < 0 {
+            report.warning(
+                format!("{path}.ep_revise"),
+                format!("第1-{}集修正后会变成0", -(ep_revise as i32)),
+            );
+        }
+    }
+}
+
+async fn check_online(config: &Config, report: &mut Report) {
+    let downloaders = std::iter::once(("downloader".to_string(), &config.downloader)).chain(
+        config
+            .downloaders
+            .iter()
+            .map(|d| (format!("downloaders[{}]", d.name), &d.downloader)),
+    );
+    for (path, downloader) in downloaders {
+        if let Err(e) = dl::get_client(downloader).torrent_get().await {
+            report.error(path, format!("无法连接下载器: {e}"));
+        }
+    }
+
+    let urls = config
+        .mikan
+        .iter()
+        .map(|m| (format!("mikan[{}].url", m.name), &m.url))
+        .chain(config.collections.iter().map(|c| {
+            (
+                format!("collections[{}].torrent_url", c.name),
+                &c.torrent_url,
+            )
+        }))
+        .chain(config.subtitle_packs.iter().map(|p| {
+            (
+                format!("subtitle_packs[{}].torrent_url", p.name),
+                &p.torrent_url,
+            )
+        }))
+        .filter(|(_, url)| url.starts_with("http://") || url.starts_with("https://"))
+        .collect::<Vec<_>>();
+    let results =
+        join_all(urls.iter().map(|(_, url)| async move {
+            CLIENT.get(url.as_str()).send().await?.error_for_status()
+        }))
+        .await;
+    for ((path, _), result) in urls.into_iter().zip(results) {
+        if let Err(e) = result {
+            // 已经完成的合集的种子链接失效不影响检查
+            report.warning(path, format!("无法访问: {e}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_validate() {
+        // Config::parse 会编译 special_mappings 中的正则, 这里直接反序列化
+        let config: Config = toml::from_str(
+            r#"
+            check_interval = 10
+            res_api = "dmhy"
+
+            [downloader]
+            type = "transmission"
+            url = "localhost:9091/transmission/rpc"
+            user = "admin"
+            password = "123123"
+
+            [link]
+            enable = true
+            path = "/muuf/not/exist"
+
+            [[mikan]]
+            url = "https://mikanani.me/RSS/Bangumi?bangumiId=1"
+            name = "a"
+            ep_revise = -12
+            episode_regex = '第\d+话'
+            downloader = "nas"
+
+            [[mikan]]
+            url = "https://mikanani.me/RSS/Bangumi?bangumiId=1"
+            name = "b"
+            title_rewrite = [{ pattern = "(", replace = "" }]
+
+            [[collections]]
+            torrent_url = "https://example.com/a.torrent"
+            name = "c"
+            title = "c"
+            special_mappings = [{ file_name = "[", name = "SP", match_and_replace = true }]
+            "#,
+        )
+        .unwrap();
+        let report = validate(&config, false).await;
+        let paths = report
+            .issues
+            .iter()
+            .map(|i| (i.level, i.path.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                (Level::Error, "mikan[a].episode_regex"),
+                (Level::Error, "mikan[b].title_rewrite[0]"),
+                (Level::Error, "collections[c].special_mappings[0]"),
+                (Level::Error, "downloader.url"),
+                (Level::Error, "mikan[a].downloader"),
+                (Level::Error, "link.path"),
+                (Level::Error, "mikan[b]"),
+                (Level::Warning, "mikan[a].ep_revise"),
+            ]
+        );
+        assert!(!report.valid);
+        assert_eq!(report.errors, 7);
+        assert_eq!(report.warnings, 1);
+    }
+}
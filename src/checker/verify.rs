@@ -0,0 +1,226 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::UNIX_EPOCH,
+};
+
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    config::{write_atomic, Link},
+    get_data_dir,
+};
+
+const CRC32_CACHE_FILE_NAME: &str = "crc32_cache.json";
+
+/// 文件路径 -> 计算过的CRC32, 文件大小或修改时间变化时重新计算
+static CRC32_CACHE: LazyLock<Mutex<Crc32Cache>> = LazyLock::new(|| {
+    Mutex::new(Crc32Cache {
+        entries: load_cache().unwrap_or_default(),
+        dirty: false,
+    })
+});
+
+struct Crc32Cache {
+    entries: HashMap<String, Crc32Entry>,
+    /// 有新计算的CRC32还没有保存
+    dirty: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct Crc32Entry {
+    len: u64,
+    modified: u64,
+    crc32: String,
+}
+
+fn cache_path() -> PathBuf {
+    get_data_dir().join(CRC32_CACHE_FILE_NAME)
+}
+
+fn load_cache() -> Result<HashMap<String, Crc32Entry>> {
+    Ok(serde_json::from_str(&fs::read_to_string(cache_path())?)?)
+}
+
+/// 每次检查结束时调用, 有新计算的CRC32时写入一次
+pub fn save_crc32_cache() -> Result<()> {
+    save_cache_to(&CRC32_CACHE, &cache_path())
+}
+
+fn save_cache_to(cache: &Mutex<Crc32Cache>, path: &Path) -> Result<()> {
+    let content = {
+        let mut cache = cache
+            .lock()
+            .map_err(|e| eyre!("lock crc32 cache failed: {e}"))?;
+        if !cache.dirty {
+            return Ok(());
+        }
+        cache.dirty = false;
+        serde_json::to_string(&cache.entries)?
+    };
+    write_atomic(path, &content).inspect_err(|_| {
+        if let Ok(mut cache) = cache.lock() {
+            cache.dirty = true;
+        }
+    })
+}
+
+/// 已经通知过校验失败的文件, 避免每次检查都重复通知
+static NOTIFIED_MISMATCH: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/**
+ * 链接前的CRC32校验, 没有开启 verify_crc32 或者标题中没有CRC32时直接通过
+ * 校验失败时打印并通知, 每个文件通知成功后不再重复通知
+ */
+pub async fn verify_before_link(
+    original: &str,
+    expected: Option<&str>,
+    link_file_name: &str,
+    link_config: &Link,
+) -> Result<bool> {
+    let Some(expected) = expected else {
+        return Ok(true);
+    };
+    if !link_config.verify_crc32 {
+        return Ok(true);
+    }
+    // 读取失败的文件也算校验失败, 不影响同一个种子中的其他文件
+    match verify_crc32(original, expected).await {
+        Ok(true) => return Ok(true),
+        Ok(false) => println!("CRC32校验失败, 期望{expected}, 不链接: {original}"),
+        Err(e) => println!("CRC32校验失败, 无法读取文件, 不链接: {original}: {e}"),
+    }
+    let Some(notify) = &link_config.notify else {
+        return Ok(false);
+    };
+    let notified = NOTIFIED_MISMATCH
+        .lock()
+        .map_err(|e| eyre!("lock notified mismatch failed: {e}"))?
+        .contains(original);
+    if notified {
+        return Ok(false);
+    }
+    // 通知失败不影响其他订阅的检查, 下次检查时重试
+    match notify.crc32_mismatch(link_file_name).await {
+        Ok(()) => {
+            NOTIFIED_MISMATCH
+                .lock()
+                .map_err(|e| eyre!("lock notified mismatch failed: {e}"))?
+                .insert(original.to_string());
+        }
+        Err(e) => error!("发送CRC32校验失败的通知失败: {e}"),
+    }
+    Ok(false)
+}
+
+/// 计算文件的CRC32并和标题中的比较, 计算在阻塞线程中进行, 不会卡住async runtime
+pub async fn verify_crc32(path: &str, expected: &str) -> Result<bool> {
+    let path = path.to_string();
+    let crc32 = tokio::task::spawn_blocking(move || cached_file_crc32(&path)).await??;
+    Ok(crc32.eq_ignore_ascii_case(expected))
+}
+
+fn cached_file_crc32(path: &str) -> Result<String> {
+    let metadata = fs::metadata(path)?;
+    let len = metadata.len();
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
+    if let Some(entry) = CRC32_CACHE
+        .lock()
+        .map_err(|e| eyre!("lock crc32 cache failed: {e}"))?
+        .entries
+        .get(path)
+    {
+        if entry.len == len && entry.modified == modified {
+            return Ok(entry.crc32.clone());
+        }
+    }
+
+    let crc32 = format!("{:08X}", file_crc32(path)?);
+    let mut cache = CRC32_CACHE
+        .lock()
+        .map_err(|e| eyre!("lock crc32 cache failed: {e}"))?;
+    cache.entries.insert(
+        path.to_string(),
+        Crc32Entry {
+            len,
+            modified,
+            crc32: crc32.clone(),
+        },
+    );
+    cache.dirty = true;
+    Ok(crc32)
+}
+
+fn file_crc32(path: &str) -> Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("muuf-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_crc32() {
+        let dir = temp_dir("crc32");
+        let path = dir.join("file");
+        fs::write(&path, "123456789").unwrap();
+        assert_eq!(file_crc32(path.to_str().unwrap()).unwrap(), 0xCBF43926);
+        assert!(file_crc32(dir.join("missing").to_str().unwrap()).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_cache() {
+        let dir = temp_dir("crc32-cache");
+        let path = dir.join(CRC32_CACHE_FILE_NAME);
+        let cache = Mutex::new(Crc32Cache {
+            entries: HashMap::new(),
+            dirty: false,
+        });
+        // 没有新的CRC32时不写
+        save_cache_to(&cache, &path).unwrap();
+        assert!(!path.exists());
+        {
+            let mut cache = cache.lock().unwrap();
+            for i in 0..3 {
+                cache.entries.insert(
+                    format!("/downloads/{i}.mkv"),
+                    Crc32Entry {
+                        len: i,
+                        modified: 0,
+                        crc32: "CBF43926".to_string(),
+                    },
+                );
+            }
+            cache.dirty = true;
+        }
+        save_cache_to(&cache, &path).unwrap();
+        let saved: HashMap<String, Crc32Entry> =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.len(), 3);
+        assert!(!cache.lock().unwrap().dirty);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,465 @@
+use color_eyre::eyre::{bail, eyre, Result};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value as Json;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, Item, Table, TableLike, Value};
+
+/// 数组中的表按这些字段对应, 修改了其他字段的订阅只改动变化的部分
+const IDENTITY_KEYS: [&str; 4] = ["id", "url", "torrent_url", "name"];
+
+/**
+ * 有 original 时只修改变化的部分
+ * 合并失败时返回错误, 不保存, 重新生成整个文件会丢掉注释和格式, 只有 original 为空时才重新生成
+ */
+pub fn to_toml<T: Serialize + DeserializeOwned + JsonSchema>(
+    value: &T,
+    original: Option<&str>,
+) -> Result<String> {
+    let generated = toml::to_string(value)?;
+    let Some(original) = original else {
+        return Ok(generated);
+    };
+    match update_document::<T>(original, &generated) {
+        Ok(merged) => Ok(merged),
+        Err(_) if original.trim().is_empty() => Ok(generated),
+        Err(e) => Err(eyre!(
+            "无法合并到原来的配置文件, 为了保留注释和格式没有保存: {e}"
+        )),
+    }
+}
+
+/**
+ * 把 new 的内容合并到 original 中, 没有变化的部分保留原来的注释和格式
+ * 用补全默认值后的 original 和 new 的 toml::Value 比较哪些部分有变化, 原来省略了的默认值不会添加
+ * 新增的部分去掉和 serde 默认值(schema 中的 default)相同的字段
+ */
+fn update_document<T: DeserializeOwned + Serialize + JsonSchema>(
+    original: &str,
+    new: &str,
+) -> Result<String> {
+    let normalize = |content: &str| -> Result<toml::Value> {
+        Ok(toml::Value::try_from(toml::from_str::<T>(content)?)?)
+    };
+    let after: toml::Value = toml::from_str(new)?;
+    let mut merger = Merger {
+        before: normalize(original).or_else(|_| toml::from_str(original))?,
+        after: after.clone(),
+        schema: serde_json::to_value(schemars::schema_for!(T))?,
+        old_path: vec![],
+        path: vec![],
+    };
+    let merged = merger.merge(original, new)?;
+    if normalize(&merged)? != after {
+        bail!("合并后的配置不一致")
+    }
+    Ok(merged)
+}
+
+struct Merger {
+    /// 补全默认值后的 original 和 new, original 不能解析时用原样的 original
+    before: toml::Value,
+    after: toml::Value,
+    /// 类型的 JSON Schema, 用来查字段的默认值
+    schema: Json,
+    /// 当前位置在 original 和 new 中的路径, 只有数组的下标可能不同
+    old_path: Vec<String>,
+    path: Vec<String>,
+}
+
+impl Merger {
+    fn merge(&mut self, original: &str, new: &str) -> Result<String> {
+        let mut doc: DocumentMut = original.parse()?;
+        let new: DocumentMut = new.parse()?;
+        self.old_path.clear();
+        self.path.clear();
+        self.merge_table_like(doc.as_table_mut(), detach_table(new.as_table().clone()));
+        Ok(doc.to_string())
+    }
+
+    fn child(&mut self, key: &str) -> &mut Self {
+        self.old_path.push(key.to_string());
+        self.path.push(key.to_string());
+        self
+    }
+
+    /// 数组中的元素, old 为 None 时是新增的
+    fn element(&mut self, old: Option<usize>, new: usize) -> &mut Self {
+        self.old_path
+            .push(old.map_or_else(|| "-".to_string(), |i| i.to_string()));
+        self.path.push(new.to_string());
+        self
+    }
+
+    fn parent(&mut self) {
+        self.old_path.pop();
+        self.path.pop();
+    }
+
+    /// 当前位置的值没有变化, 包括原来省略了默认值的情况
+    fn unchanged(&self) -> bool {
+        match (
+            lookup(&self.before, &self.old_path),
+            lookup(&self.after, &self.path),
+        ) {
+            (Some(before), Some(after)) => before == after,
+            _ => false,
+        }
+    }
+
+    fn same_element(&mut self, old: usize, new: usize) -> bool {
+        let unchanged = self.element(Some(old), new).unchanged();
+        self.parent();
+        unchanged
+    }
+
+    /// 当前位置的字段和 serde 的默认值相同, 可以省略
+    fn can_skip(&self) -> bool {
+        match (
+            schema_default(&self.schema, &self.path),
+            lookup(&self.after, &self.path),
+        ) {
+            (Some(default), Some(value)) => {
+                serde_json::to_value(value).ok().as_ref() == Some(default)
+            }
+            _ => false,
+        }
+    }
+
+    /// 数组中和 new 对应的原来的元素: 唯一字段相同, 或者没有变化
+    fn find_element(&mut self, unused: &[bool], new: usize) -> Option<usize> {
+        let key = identity_at(&self.after, &self.path, new);
+        if key.is_some() {
+            if let Some(j) = (0..unused.len())
+                .find(|&j| unused[j] && identity_at(&self.before, &self.old_path, j) == key)
+            {
+                return Some(j);
+            }
+        }
+        if unused.get(new) == Some(&true) && self.same_element(new, new) {
+            return Some(new);
+        }
+        (0..unused.len()).find(|&j| unused[j] && self.same_element(j, new))
+    }
+
+    fn merge_item(&mut self, old: &mut Item, new: Item) {
+        if self.unchanged() {
+            return;
+        }
+        // 保持原来的写法, 内联表还是内联表, 原来是空数组时用新的写法
+        let new = match (&*old, new) {
+            (Item::Value(Value::Array(array)), new) if array.is_empty() => {
+                *old = self.new_item(new);
+                return;
+            }
+            (Item::Value(_), new @ (Item::Table(_) | Item::ArrayOfTables(_))) => {
+                new.into_value().map_or_else(|new| new, Item::Value)
+            }
+            (Item::Table(_), Item::Value(Value::InlineTable(table))) => {
+                Item::Table(table.into_table())
+            }
+            (Item::ArrayOfTables(_), Item::Value(Value::Array(array)))
+                if array.iter().all(Value::is_inline_table) =>
+            {
+                Item::ArrayOfTables(
+                    array
+                        .into_iter()
+                        .filter_map(|v| match v {
+                            Value::InlineTable(table) => Some(table.into_table()),
+                            _ => None,
+                        })
+                        .collect(),
+                )
+            }
+            (_, new) => new,
+        };
+        match (old, new) {
+            (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) => {
+                self.merge_array_of_tables(old, new)
+            }
+            (Item::Value(old), Item::Value(new)) => self.merge_value(old, new),
+            (Item::Table(old), Item::Table(new)) => self.merge_table_like(old, new),
+            (old, new) => *old = self.new_item(new),
+        }
+    }
+
+    fn merge_value(&mut self, old: &mut Value, new: Value) {
+        if self.unchanged() {
+            return;
+        }
+        match (old, new) {
+            (Value::InlineTable(old), Value::InlineTable(new)) => {
+                self.merge_table_like(old, new.into_table())
+            }
+            (Value::Array(old), Value::Array(new)) => self.merge_array(old, new),
+            (old, mut new) => {
+                *new.decor_mut() = old.decor().clone();
+                *old = new;
+            }
+        }
+    }
+
+    fn merge_table_like(&mut self, old: &mut dyn TableLike, new: Table) {
+        let removed = old
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .filter(|key| !new.contains_key(key))
+            .collect::<Vec<_>>();
+        for key in removed {
+            old.remove(&key);
+        }
+        for (key, item) in new {
+            self.child(&key);
+            match old.get_mut(&key) {
+                Some(old_item) => {
+                    let was_value = old_item.is_value();
+                    self.merge_item(old_item, item);
+                    // 从 key = [] 变成 [[key]] 时去掉原来 key 的格式
+                    if was_value && !old_item.is_value() {
+                        if let Some(mut key) = old.key_mut(&key) {
+                            key.fmt();
+                        }
+                    }
+                }
+                None if self.unchanged() || self.can_skip() => {}
+                None => {
+                    old.insert(&key, self.new_item(item));
+                }
+            }
+            self.parent();
+        }
+    }
+
+    /// 长度不变时逐个合并, 否则保留没有变化的元素, 内联表按唯一字段对应
+    fn merge_array(&mut self, old: &mut Array, new: Array) {
+        if old.len() == new.len() {
+            for (i, (old, new)) in old.iter_mut().zip(new).enumerate() {
+                self.element(Some(i), i).merge_value(old, new);
+                self.parent();
+            }
+            return;
+        }
+        let decor = old.iter().last().map(|v| v.decor().clone());
+        let mut unused = old.iter().cloned().map(Some).collect::<Vec<_>>();
+        old.clear();
+        for (i, new) in new.into_iter().enumerate() {
+            let available = unused.iter().map(Option::is_some).collect::<Vec<_>>();
+            let index = self.find_element(&available, i);
+            match index.and_then(|j| Some((j, unused[j].take()?))) {
+                Some((j, mut kept)) => {
+                    self.element(Some(j), i).merge_value(&mut kept, new);
+                    self.parent();
+                    old.push_formatted(kept);
+                }
+                None => {
+                    let mut new = match new {
+                        Value::InlineTable(table) => Value::InlineTable(
+                            self.element(None, i)
+                                .new_table(table.into_table())
+                                .into_inline_table(),
+                        ),
+                        new => {
+                            self.element(None, i);
+                            new
+                        }
+                    };
+                    self.parent();
+                    if let Some(decor) = &decor {
+                        *new.decor_mut() = decor.clone();
+                    }
+                    old.push_formatted(new);
+                }
+            }
+        }
+    }
+
+    /// 订阅按唯一字段对应, 新增的追加在最后
+    fn merge_array_of_tables(&mut self, old: &mut ArrayOfTables, new: ArrayOfTables) {
+        let same_len = old.len() == new.len();
+        let mut unused = old.iter().cloned().map(Some).collect::<Vec<_>>();
+        let mut merged = ArrayOfTables::new();
+        for (i, new) in new.into_iter().enumerate() {
+            let available = unused.iter().map(Option::is_some).collect::<Vec<_>>();
+            let index = self
+                .find_element(&available, i)
+                .or_else(|| (same_len && available[i]).then_some(i));
+            match index.and_then(|j| Some((j, unused[j].take()?))) {
+                Some((j, mut table)) => {
+                    self.element(Some(j), i).merge_table_like(&mut table, new);
+                    merged.push(table);
+                }
+                None => {
+                    let table = self.element(None, i).new_table(new);
+                    merged.push(table);
+                }
+            }
+            self.parent();
+        }
+        *old = merged;
+    }
+
+    /// 新增的部分, 去掉可以省略的字段
+    fn new_item(&mut self, item: Item) -> Item {
+        match item {
+            Item::Table(table) => Item::Table(self.new_table(table)),
+            Item::ArrayOfTables(array) => {
+                let mut tables = ArrayOfTables::new();
+                for (i, table) in array.into_iter().enumerate() {
+                    tables.push(self.element(None, i).new_table(table));
+                    self.parent();
+                }
+                Item::ArrayOfTables(tables)
+            }
+            item => item,
+        }
+    }
+
+    fn new_table(&mut self, table: Table) -> Table {
+        let mut created = Table::new();
+        created.set_implicit(table.is_implicit());
+        for (key, item) in table {
+            self.child(&key);
+            if !self.can_skip() {
+                let item = self.new_item(item);
+                created.insert(&key, item);
+            }
+            self.parent();
+        }
+        created
+    }
+}
+
+/// 数组 path 中第 index 个元素的唯一字段
+fn identity_at<'a>(
+    value: &'a toml::Value,
+    path: &[String],
+    index: usize,
+) -> Option<(&'a str, &'a str)> {
+    let mut path = path.to_vec();
+    path.push(index.to_string());
+    lookup(value, &path).and_then(identity)
+}
+
+fn identity(value: &toml::Value) -> Option<(&str, &str)> {
+    let table = value.as_table()?;
+    IDENTITY_KEYS
+        .iter()
+        .find_map(|key| Some((*key, table.get(*key)?.as_str()?)))
+}
+
+/// 生成的表带着新文档中的位置, 重新创建后排在原文档中相邻的表后面
+fn detach_table(table: Table) -> Table {
+    let mut detached = Table::new();
+    detached.set_implicit(table.is_implicit());
+    detached.set_dotted(table.is_dotted());
+    for (key, item) in table {
+        let item = match item {
+            Item::Table(table) => Item::Table(detach_table(table)),
+            Item::ArrayOfTables(array) => {
+                Item::ArrayOfTables(array.into_iter().map(detach_table).collect())
+            }
+            item => item,
+        };
+        detached.insert(&key, item);
+    }
+    detached
+}
+
+/// schema 中 path 对应字段的默认值, 数组的元素没有默认值
+fn schema_default<'a>(root: &'a Json, path: &[String]) -> Option<&'a Json> {
+    let mut schema = root;
+    let mut field = None;
+    for key in path {
+        let resolved = resolve(root, schema);
+        (schema, field) = match resolved.get("items") {
+            Some(items) => (items, None),
+            None => {
+                let property = resolved.get("properties")?.get(key)?;
+                (property, Some(property))
+            }
+        };
+    }
+    field?.get("default")
+}
+
+/// 跟随 $ref, 只有一个非 null 选项的 allOf 和 anyOf(Option)
+fn resolve<'a>(root: &'a Json, schema: &'a Json) -> &'a Json {
+    let definition = schema
+        .get("$ref")
+        .and_then(Json::as_str)
+        .and_then(|r| r.strip_prefix("#/definitions/"))
+        .and_then(|name| root.get("definitions")?.get(name));
+    if let Some(definition) = definition {
+        return resolve(root, definition);
+    }
+    for key in ["allOf", "anyOf"] {
+        if let Some(Json::Array(schemas)) = schema.get(key) {
+            let mut options = schemas
+                .iter()
+                .filter(|s| s.get("type").and_then(Json::as_str) != Some("null"));
+            if let (Some(only), None) = (options.next(), options.next()) {
+                return resolve(root, only);
+            }
+        }
+    }
+    schema
+}
+
+pub(super) fn lookup<'a>(value: &'a toml::Value, path: &[String]) -> Option<&'a toml::Value> {
+    path.iter().try_fold(value, |value, key| match value {
+        toml::Value::Table(table) => table.get(key),
+        toml::Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// 两个值不同的路径, 数组中的元素用下标
+pub(super) fn diff(
+    a: &toml::Value,
+    b: &toml::Value,
+    path: &mut Vec<String>,
+    differences: &mut Vec<String>,
+) {
+    match (a, b) {
+        (toml::Value::Table(a), toml::Value::Table(b)) => {
+            for key in a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))) {
+                path.push(key.clone());
+                match (a.get(key), b.get(key)) {
+                    (Some(a), Some(b)) => diff(a, b, path, differences),
+                    _ => differences.push(path.join(".")),
+                }
+                path.pop();
+            }
+        }
+        (toml::Value::Array(a), toml::Value::Array(b)) if a.len() == b.len() => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                path.push(i.to_string());
+                diff(a, b, path, differences);
+                path.pop();
+            }
+        }
+        (a, b) if a != b => differences.push(path.join(".")),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_schema_default() {
+        let schema = serde_json::to_value(schemars::schema_for!(Config)).unwrap();
+        let path = |p: &str| p.split('.').map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            schema_default(&schema, &path("collections.0.extras")),
+            Some(&Json::from("off"))
+        );
+        assert_eq!(
+            schema_default(&schema, &path("mikan.0.title_contain")),
+            Some(&Json::Array(vec![]))
+        );
+        assert_eq!(schema_default(&schema, &path("mikan.0.url")), None);
+        assert_eq!(schema_default(&schema, &path("mikan.0")), None);
+    }
+}
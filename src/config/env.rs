@@ -0,0 +1,181 @@
+/*
+   用环境变量覆盖配置文件中的值, 方便在容器中部署
+   MUUF_DOWNLOADER_URL       [downloader] url
+   MUUF_DOWNLOADER_USER      [downloader] user
+   MUUF_DOWNLOADER_PASSWORD  [downloader] password
+   MUUF_CHECK_INTERVAL       check_interval
+   MUUF_LINK_PATH            [link] path
+   每个都可以用 _FILE 结尾的变量指定一个文件, 读取文件的内容, 如 MUUF_DOWNLOADER_PASSWORD_FILE=/run/secrets/password
+   保存配置时写回文件中原来的值, 环境变量中的密码不会写到配置文件中
+*/
+use std::fs;
+
+use color_eyre::eyre::{bail, eyre, Result};
+
+use super::{Config, Downloader};
+use crate::PROJECT_NAME;
+
+struct Override {
+    name: &'static str,
+    get: fn(&Config) -> Option<String>,
+    set: fn(&mut Config, String) -> Result<()>,
+}
+
+const OVERRIDES: [Override; 5] = [
+    Override {
+        name: "DOWNLOADER_URL",
+        get: |config| match &config.downloader {
+            Downloader::Transmission(t) => Some(t.url.clone()),
+        },
+        set: |config, value| {
+            match &mut config.downloader {
+                Downloader::Transmission(t) => t.url = value,
+            }
+            Ok(())
+        },
+    },
+    Override {
+        name: "DOWNLOADER_USER",
+        get: |config| match &config.downloader {
+            Downloader::Transmission(t) => Some(t.user.clone()),
+        },
+        set: |config, value| {
+            match &mut config.downloader {
+                Downloader::Transmission(t) => t.user = value,
+            }
+            Ok(())
+        },
+    },
+    Override {
+        name: "DOWNLOADER_PASSWORD",
+        get: |config| match &config.downloader {
+            Downloader::Transmission(t) => Some(t.password.clone()),
+        },
+        set: |config, value| {
+            match &mut config.downloader {
+                Downloader::Transmission(t) => t.password = value,
+            }
+            Ok(())
+        },
+    },
+    Override {
+        name: "CHECK_INTERVAL",
+        get: |config| Some(config.check_interval.to_string()),
+        set: |config, value| {
+            config.check_interval = value
+                .trim()
+                .parse()
+                .map_err(|e| eyre!("CHECK_INTERVAL {value}: {e}"))?;
+            Ok(())
+        },
+    },
+    Override {
+        name: "LINK_PATH",
+        get: |config| config.link.as_ref().map(|link| link.path.clone()),
+        set: |config, value| {
+            let Some(link) = &mut config.link else {
+                bail!("没有 [link] 配置, 不能覆盖 LINK_PATH");
+            };
+            link.path = value;
+            Ok(())
+        },
+    },
+];
+
+/// 被覆盖的值在配置文件中原来的值
+#[derive(Debug, Clone)]
+pub struct Overridden {
+    name: &'static str,
+    original: Option<String>,
+}
+
+pub fn apply(config: &mut Config) -> Result<()> {
+    apply_with(config, |name| {
+        let name = format!("{}_{name}", PROJECT_NAME.as_str());
+        if let Ok(value) = std::env::var(&name) {
+            return Ok(Some(value));
+        }
+        match std::env::var(format!("{name}_FILE")) {
+            Ok(file) => Ok(Some(
+                fs::read_to_string(&file)
+                    .map_err(|e| eyre!("{name}_FILE {file}: {e}"))?
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string(),
+            )),
+            Err(_) => Ok(None),
+        }
+    })
+}
+
+fn apply_with(config: &mut Config, lookup: impl Fn(&str) -> Result<Option<String>>) -> Result<()> {
+    for o in &OVERRIDES {
+        if let Some(value) = lookup(o.name)? {
+            let original = (o.get)(config);
+            (o.set)(config, value)?;
+            config.overridden.push(Overridden {
+                name: o.name,
+                original,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 保存前恢复配置文件中原来的值
+pub fn restore(config: &mut Config) -> Result<()> {
+    for overridden in std::mem::take(&mut config.overridden) {
+        let Some(o) = OVERRIDES.iter().find(|o| o.name == overridden.name) else {
+            continue;
+        };
+        if let Some(original) = overridden.original {
+            (o.set)(config, original)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_overrides() {
+        let mut config = Config::parse(
+            r#"
+            check_interval = 10
+            res_api = "dmhy"
+
+            [downloader]
+            type = "transmission"
+            url = "https://192.168.1.1:8080/transmission/rpc"
+            user = "admin"
+            password = "123123"
+            "#,
+        )
+        .unwrap();
+        apply_with(&mut config, |name| {
+            Ok(match name {
+                "DOWNLOADER_PASSWORD" => Some("secret".to_string()),
+                "CHECK_INTERVAL" => Some("600".to_string()),
+                _ => None,
+            })
+        })
+        .unwrap();
+        let Downloader::Transmission(t) = &config.downloader;
+        assert_eq!(t.password, "secret");
+        assert_eq!(config.check_interval, 600);
+
+        let mut saved = config.clone();
+        restore(&mut saved).unwrap();
+        let Downloader::Transmission(t) = &saved.downloader;
+        assert_eq!(t.password, "123123");
+        assert_eq!(saved.check_interval, 10);
+
+        // 没有 [link] 时不能覆盖 link path
+        assert!(apply_with(&mut config, |name| Ok(
+            (name == "LINK_PATH").then(|| "/media".to_string())
+        ))
+        .is_err());
+        assert!(apply_with(&mut config, |_| Ok(Some("ten".to_string()))).is_err());
+    }
+}
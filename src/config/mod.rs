@@ -1,23 +1,55 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
 use chrono::NaiveDateTime;
 use color_eyre::eyre::{eyre, Result};
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::{get_data_dir, notify::Notify};
+use crate::{get_data_dir, notify::Notify, PROJECT_NAME};
+
+mod edit;
+mod env;
+mod reload;
+mod split;
+mod store;
+pub use reload::{changes, ConfigWatcher};
+pub use split::Subscription;
+pub use store::{ConfigStore, RevisionMismatch, CONFIG_STORE};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Config {
     #[serde(default)]
     pub rules: Vec<Rule>,
     #[serde(default)]
     pub mikan: Vec<Mikan>,
+    /// 默认的下载器
     pub downloader: Downloader,
+    /// 其他有名字的下载器, 订阅中用 downloader = "名字" 指定
+    #[serde(default)]
+    pub downloaders: Vec<NamedDownloader>,
     pub res_api: ResApi,
     pub proxy: Option<Proxy>,
     pub check_interval: u64,
     pub link: Option<Link>,
     #[serde(default)]
     pub collections: Vec<Collection>,
+    #[serde(default)]
+    pub subtitle_packs: Vec<SubtitlePack>,
+    pub seeding: Option<Seeding>,
+    pub trackers: Option<Trackers>,
+    pub server: Option<Server>,
+    /// 从订阅目录中读取的订阅
+    #[serde(skip)]
+    files: Vec<split::SubscriptionFile>,
+    /// 被环境变量覆盖的值
+    #[serde(skip)]
+    overridden: Vec<env::Overridden>,
 }
 
 impl PartialEq for Config {
@@ -25,28 +57,33 @@ impl PartialEq for Config {
         self.rules == other.rules
             && self.mikan == other.mikan
             && self.downloader == other.downloader
+            && self.downloaders == other.downloaders
             && self.res_api == other.res_api
             && self.proxy == other.proxy
             && self.check_interval == other.check_interval
             && self.link == other.link
             && self.collections == other.collections
+            && self.subtitle_packs == other.subtitle_packs
+            && self.seeding == other.seeding
+            && self.trackers == other.trackers
+            && self.server == other.server
     }
 }
 
-#[derive(Debug, PartialEq, Deserialize, Serialize, Hash, Eq)]
+#[derive(Debug, PartialEq, Deserialize, Serialize, JsonSchema, Hash, Eq, Clone)]
 #[serde(rename_all(deserialize = "lowercase", serialize = "lowercase"))]
 pub enum ResApi {
     Dmhy,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Proxy {
     pub scheme: String,
     pub username: Option<String>,
     pub password: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 #[serde(
     tag = "type",
     rename_all(deserialize = "lowercase", serialize = "lowercase")
@@ -55,14 +92,21 @@ pub enum Downloader {
     Transmission(TransmissionConfig),
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct NamedDownloader {
+    pub name: String,
+    #[serde(flatten)]
+    pub downloader: Downloader,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct TransmissionConfig {
     pub url: String,
     pub user: String,
     pub password: String,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Rule {
     pub name: String,
     pub keywords: Vec<String>,
@@ -72,9 +116,14 @@ pub struct Rule {
     pub res_type_id: Option<i32>,
     pub res_type_name: Option<String>,
     pub publish_after: Option<NaiveDateTime>,
+    /// 下载器的名字, 不设置时使用默认的下载器
+    pub downloader: Option<String>,
+    /// API 中的id, 通过 API 新建时生成
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Mikan {
     pub url: String,
     pub name: String,
@@ -90,34 +139,112 @@ pub struct Mikan {
     pub ep_revise: i8,
     #[serde(default)]
     pub season: Option<u8>,
+    pub episode_regex: Option<String>,
+    pub season_regex: Option<String>,
+    #[serde(default)]
+    pub title_rewrite: Vec<TitleRewrite>,
+    #[serde(default)]
+    pub tag_filter: Vec<TagFilter>,
+    /// 下载器的名字, 不设置时使用默认的下载器
+    pub downloader: Option<String>,
+    /// API 中的id, 通过 API 新建时生成
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct MikanItem {
     pub title: String,
     pub url: String,
 }
 
-#[derive(Debug, PartialEq, Deserialize, Serialize, Clone)]
+#[derive(Debug, PartialEq, Deserialize, Serialize, JsonSchema, Clone, Default)]
 pub struct Collection {
     pub torrent_url: String,
     pub name: String,
     pub title: String,
+    /// 为空时根据文件夹名和文件名自动推断
     #[serde(default)]
     pub season_folders: Vec<SeasonFolder>,
     #[serde(default)]
     pub special_mappings: Vec<SpecialMapping>,
     #[serde(default)]
     pub external_subtitle: bool,
+    pub episode_regex: Option<String>,
+    pub season_regex: Option<String>,
+    #[serde(default)]
+    pub title_rewrite: Vec<TitleRewrite>,
+    /// NCOP NCED Menu PV CM SP 等特典的处理方式, special_mappings 优先
+    #[serde(default)]
+    pub extras: ExtrasMode,
+    /// 种子中文件路径的glob, 不为空时只下载匹配的文件
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// 种子中文件路径的glob, 匹配的文件不下载, 优先于 include, 如 `CDs/**`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// 下载器的名字, 不设置时使用默认的下载器
+    pub downloader: Option<String>,
+    /// API 中的id, 通过 API 新建时生成
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone, Copy, Default)]
+#[serde(rename_all(deserialize = "lowercase", serialize = "lowercase"))]
+pub enum ExtrasMode {
+    /// 和普通文件一样按 season_folders 处理
+    #[default]
+    Off,
+    /// SP 作为S00, 其他链接到 extras trailers featurettes 文件夹
+    Folders,
+    /// 全部作为S00
+    Specials,
+    /// 不链接
+    Skip,
+}
+
+/**
+ * 单独发布的字幕包, 和同名(name)的 mikan 或 collection 订阅配对
+ * 按解析出的集数把字幕链接到已经链接好的视频旁边
+ */
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct SubtitlePack {
+    pub torrent_url: String,
+    pub name: String,
+    pub title: String,
+    /// 不设置时使用从字幕文件名解析出的season
+    pub season: Option<u8>,
+    #[serde(default)]
+    pub ep_revise: i8,
+    pub episode_regex: Option<String>,
+    /// 下载器的名字, 不设置时使用默认的下载器
+    pub downloader: Option<String>,
+    /// API 中的id, 通过 API 新建时生成
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct TitleRewrite {
+    pub pattern: String,
+    pub replace: String,
+}
+
+/// 解析出的字段(见 parser::EP_TAGS)需要匹配 pattern, 如 { tag = "video_codec", pattern = "HEVC" }
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct TagFilter {
+    pub tag: String,
+    pub pattern: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct SeasonFolder {
     pub season: u8,
     pub folder: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct SpecialMapping {
     pub file_name: String,
     pub name: String,
@@ -142,50 +269,190 @@ impl PartialEq for SpecialMapping {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Link {
     pub enable: bool,
     pub path: String,
     #[serde(default)]
     pub dry_run: bool,
     pub notify: Option<Notify>,
+    /// 标题解析的可信度(0-100)低于此值时不链接
+    #[serde(default)]
+    pub min_confidence: u8,
+    /// 链接前计算文件的CRC32, 和标题中的 [ABCD1234] 不一致时不链接
+    #[serde(default)]
+    pub verify_crc32: bool,
+    /// 外挂字幕的语言识别表, 优先于内置的
+    #[serde(default)]
+    pub subtitle_langs: Vec<SubtitleLang>,
+    /// 这个语言的外挂字幕在文件名中标记为 default, 如 "zh"
+    pub default_subtitle_lang: Option<String>,
+    /// 把字幕附带的字体链接到 {path}/{name}/fonts
+    #[serde(default)]
+    pub collect_fonts: bool,
+    /// 链接的文件名模板, 如 "{name} S{season}E{episode} [{resolution}]", 不设置时为 "{name} S{season}E{episode}"
+    pub file_name_template: Option<String>,
+}
+
+/**
+ * 做种策略, 只对 muuf 添加的种子生效
+ * 达到 seed_ratio 或 seed_days 任意一个后停止做种, 都不设置时不限制
+ * 开启 remove_after_link 时, 已经链接过的种子在停止做种时直接删除, 链接的文件是硬链接, 不受影响
+ */
+#[derive(Debug, PartialEq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct Seeding {
+    /// 分享率
+    pub seed_ratio: Option<f32>,
+    /// 做种天数
+    pub seed_days: Option<u32>,
+    #[serde(default)]
+    pub remove_after_link: bool,
+    /// 删除种子时同时删除下载的文件
+    #[serde(default)]
+    pub delete_data: bool,
+    /// 没有 muuf 标签的旧版本种子只按下载目录判断, 默认不删除它们的文件, 开启后同样删除
+    #[serde(default)]
+    pub delete_unlabelled_data: bool,
+}
+
+/**
+ * 追加到 muuf 添加的种子中的tracker, list file url 中的会合并在一起
+ * 文件和url的内容为每行一个tracker, 忽略空行和#开头的行
+ */
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct Trackers {
+    #[serde(default)]
+    pub list: Vec<String>,
+    /// 本地文件
+    pub file: Option<String>,
+    /// 定时下载的tracker列表, 如 https://ngosang.github.io/trackerslist/trackers_best.txt
+    pub url: Option<String>,
+    /// url 的刷新间隔, 默认24小时
+    pub refresh_hours: Option<u64>,
+    /// 只给连接的peer数少于这个值的种子添加, 不设置时全部添加
+    pub max_peers: Option<i64>,
+}
+
+/**
+ * API服务, 不配置时监听 0.0.0.0:3000, 不需要认证, 允许任何来源
+ * token 和 basic_auth 都配置时满足任意一个即可
+ */
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Default, Clone)]
+pub struct Server {
+    /// 默认 0.0.0.0
+    pub bind: Option<String>,
+    /// 默认 3000
+    pub port: Option<u16>,
+    /// 请求头 Authorization: Bearer {token}
+    pub token: Option<String>,
+    pub basic_auth: Option<BasicAuth>,
+    /// CORS允许的来源, 如 "http://localhost:5173", 为空时允许任何来源
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    pub tls: Option<Tls>,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct BasicAuth {
+    pub user: String,
+    pub password: String,
+}
+
+/// PEM格式的证书和私钥文件
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct Tls {
+    pub cert: String,
+    pub key: String,
+}
+
+/**
+ * 外挂字幕语言识别
+ * 字幕文件名以视频文件名开头时, 用剩下的部分精确匹配 suffixes, 如 `视频名.tc.ass` 中的 tc
+ * 否则检查字幕文件名是否包含 hints 中的任意一个, 如 `[简中]`
+ */
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct SubtitleLang {
+    pub lang: String,
+    #[serde(default)]
+    pub suffixes: Vec<String>,
+    #[serde(default)]
+    pub hints: Vec<String>,
 }
 
 const CONFIG_FILE_NAME: &str = "muuf.toml";
-// const MIKAN_CONFIG_FILE_NAME: &str = "mikan.toml";
+
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// 命令行中 --config 指定的配置文件, 要在读取配置之前设置
+pub fn set_config_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(path);
+}
 
 impl Config {
+    /// --config 指定的, 其次是环境变量 MUUF_CONFIG, 都没有时为数据目录中的 muuf.toml
+    pub fn path() -> PathBuf {
+        CONFIG_PATH
+            .get()
+            .cloned()
+            .or_else(|| {
+                std::env::var_os(format!("{}_CONFIG", PROJECT_NAME.as_str())).map(PathBuf::from)
+            })
+            .unwrap_or_else(|| get_data_dir().join(CONFIG_FILE_NAME))
+    }
+
     pub fn load() -> Result<Config> {
-        let data_dir = get_data_dir();
-        let config_str = std::fs::read_to_string(data_dir.join(CONFIG_FILE_NAME))?;
-        let mut config: Config = toml::from_str(&config_str)?;
+        Self::load_from(&Self::path())
+    }
 
+    /// 读取配置文件和旁边的订阅目录, 再用环境变量覆盖
+    pub fn load_from(path: &Path) -> Result<Config> {
+        split::ConfigFiles::read(path)?.into_config()
+    }
+
+    /// 不编译 special_mappings 中的正则, validate 中逐个检查以便报告所有的错误
+    pub fn load_uncompiled() -> Result<Config> {
+        split::ConfigFiles::read(&Self::path())?.into_uncompiled()
+    }
+
+    pub fn parse(config_str: &str) -> Result<Config> {
+        let mut config: Config = toml::from_str(config_str)?;
+        config.compile()?;
+        Ok(config)
+    }
+
+    fn compile(&mut self) -> Result<()> {
         // compile regex in collection
-        for collection in config.collections.iter_mut() {
+        for collection in self.collections.iter_mut() {
             for mapping in collection.special_mappings.iter_mut() {
                 if mapping.match_and_replace {
                     mapping.matcher = Matcher::On(Regex::new(&mapping.file_name)?);
                 }
             }
         }
-
-        // load standalone mikan configs
-        // let mikan_configs: Vec<Mikan> = toml::from_str(&std::fs::read_to_string(
-        //     data_dir.join(MIKAN_CONFIG_FILE_NAME),
-        // )?)?;
-        // config.mikan.extend(mikan_configs);
-
-        Ok(config)
+        Ok(())
     }
 
     pub fn save(&self) -> Result<()> {
-        std::fs::write(
-            get_data_dir().join(CONFIG_FILE_NAME),
-            toml::to_string(self)?,
-        )?;
+        self.save_to(&Self::path())
+    }
+
+    /// 目录中的订阅写回各自的文件, 其他的写到 path
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let mut main = self.save_split(path)?;
+        env::restore(&mut main)?;
+        let original = fs::read_to_string(path).ok();
+        let content = main.to_toml(original.as_deref())?;
+        if original.as_deref() != Some(content.as_str()) {
+            write_atomic(path, &content)?;
+        }
         Ok(())
     }
 
+    /// 有原来的配置文件时只修改变化的部分, 保留注释和格式
+    pub fn to_toml(&self, original: Option<&str>) -> Result<String> {
+        edit::to_toml(self, original)
+    }
+
     pub fn add_mikan(&mut self, mikan: Mikan) -> Result<()> {
         let maybe_pos = self.mikan.iter().position(|m| m.url == mikan.url);
         if let Some(pos) = maybe_pos {
@@ -222,6 +489,19 @@ impl Config {
         }
     }
 
+    /// 按名字查找下载器, None 为默认的下载器
+    pub fn get_downloader(&self, name: Option<&str>) -> Result<&Downloader> {
+        match name {
+            None => Ok(&self.downloader),
+            Some(name) => self
+                .downloaders
+                .iter()
+                .find(|d| d.name == name)
+                .map(|d| &d.downloader)
+                .ok_or_else(|| eyre!("没有名为{name}的下载器")),
+        }
+    }
+
     pub fn rm_collection(&mut self, url: &str) -> Result<()> {
         let index = self.collections.iter().position(|m| m.torrent_url == url);
         match index {
@@ -234,11 +514,62 @@ impl Config {
     }
 }
 
+/// 可以单独生成 JSON Schema 的配置, 订阅目录中的文件和前端的表单使用订阅的 schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// muuf.toml
+    #[default]
+    Config,
+    /// mikan.d 中的文件
+    Mikan,
+    /// collections.d 中的文件
+    Collection,
+    /// rules.d 中的文件
+    Rule,
+    /// subtitle_packs.d 中的文件
+    SubtitlePack,
+}
+
+/// 配置的 JSON Schema, 编辑器可以用来校验和补全, 仓库中的 muuf.schema.json 由它生成
+pub fn schema(kind: SchemaKind) -> String {
+    let schema = match kind {
+        SchemaKind::Config => schemars::schema_for!(Config),
+        SchemaKind::Mikan => schemars::schema_for!(Mikan),
+        SchemaKind::Collection => schemars::schema_for!(Collection),
+        SchemaKind::Rule => schemars::schema_for!(Rule),
+        SchemaKind::SubtitlePack => schemars::schema_for!(SubtitlePack),
+    };
+    serde_json::to_string_pretty(&schema).unwrap() + "\n"
+}
+
+/// 先写到同目录的临时文件再改名, 写到一半崩溃也不会损坏原文件
+pub(crate) fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use chrono::prelude::*;
 
+    #[test]
+    fn test_schema_up_to_date() {
+        // 修改配置后运行 muuf config schema > muuf.schema.json
+        assert_eq!(
+            schema(SchemaKind::Config),
+            include_str!("../../muuf.schema.json")
+        );
+        let mikan: serde_json::Value = serde_json::from_str(&schema(SchemaKind::Mikan)).unwrap();
+        assert_eq!(mikan["required"], serde_json::json!(["name", "url"]));
+    }
+
     #[test]
     fn prase_config_str() {
         let a: Config = toml::from_str(
@@ -257,11 +588,43 @@ mod tests {
         user = "admin"
         password = "123123"
 
+        [[downloaders]]
+        name = "nas"
+        type = "transmission"
+        url = "http://nas:9091/transmission/rpc"
+        user = "admin"
+        password = "456"
+
         [link]
         enable = false
         path = "/downloads/link"
         dry_run = true
         notify = { type = "Ntfy", topic = "c" }
+        min_confidence = 60
+        verify_crc32 = true
+        subtitle_langs = [{ lang = "zh", suffixes = ["jpsc"], hints = ["简日"] }]
+        default_subtitle_lang = "zh"
+        collect_fonts = true
+        file_name_template = "{name} S{season}E{episode} [{resolution}]"
+
+        [seeding]
+        seed_ratio = 2.0
+        seed_days = 14
+        remove_after_link = true
+
+        [trackers]
+        list = ["udp://tracker.opentrackr.org:1337/announce"]
+        url = "https://ngosang.github.io/trackerslist/trackers_best.txt"
+        refresh_hours = 12
+        max_peers = 5
+
+        [server]
+        bind = "127.0.0.1"
+        port = 3001
+        token = "t"
+        basic_auth = { user = "u", password = "p" }
+        allowed_origins = ["http://localhost:5173"]
+        tls = { cert = "cert.pem", key = "key.pem" }
 
         [[mikan]]
         url = "u1"
@@ -271,6 +634,9 @@ mod tests {
         external_subtitle = true
         ep_revise = -1
         season = 2
+        episode_regex = '\[(\d+)\]'
+        title_rewrite = [{ pattern = "Oshi", replace = "oshi" }]
+        tag_filter = [{ tag = "video_codec", pattern = "HEVC" }]
 
         [[mikan.extra]]
         title="2"
@@ -299,6 +665,16 @@ mod tests {
             { file_name = "a.mkv", name = "bala", match_and_replace = true },
         ]
         external_subtitle = true
+        season_regex = 'S(\d+)'
+        extras = "folders"
+        exclude = ["CDs/**", "Scans/**"]
+        downloader = "nas"
+
+        [[subtitle_packs]]
+        torrent_url = "s"
+        name = "n"
+        title = "2"
+        season = 1
         "#,
         )
         .unwrap();
@@ -318,7 +694,9 @@ mod tests {
                             .unwrap()
                     ),
                     res_type_id: None,
-                    res_type_name: None
+                    res_type_name: None,
+                    downloader: None,
+                    id: None,
                 }],
                 mikan: vec![
                     Mikan {
@@ -335,7 +713,19 @@ mod tests {
                         title_contain: vec![String::from("4")],
                         external_subtitle: true,
                         ep_revise: -1,
-                        season: Some(2)
+                        season: Some(2),
+                        episode_regex: Some(r"\[(\d+)\]".to_string()),
+                        season_regex: None,
+                        title_rewrite: vec![TitleRewrite {
+                            pattern: "Oshi".to_string(),
+                            replace: "oshi".to_string()
+                        }],
+                        tag_filter: vec![TagFilter {
+                            tag: "video_codec".to_string(),
+                            pattern: "HEVC".to_string()
+                        }],
+                        downloader: None,
+                        id: None,
                     },
                     Mikan {
                         url: "u2".to_string(),
@@ -345,7 +735,13 @@ mod tests {
                         title_contain: vec![],
                         external_subtitle: false,
                         ep_revise: 0,
-                        season: None
+                        season: None,
+                        episode_regex: None,
+                        season_regex: None,
+                        title_rewrite: vec![],
+                        tag_filter: vec![],
+                        downloader: None,
+                        id: None,
                     }
                 ],
                 downloader: Downloader::Transmission(TransmissionConfig {
@@ -353,6 +749,14 @@ mod tests {
                     user: String::from("admin"),
                     password: String::from("123123"),
                 }),
+                downloaders: vec![NamedDownloader {
+                    name: "nas".to_string(),
+                    downloader: Downloader::Transmission(TransmissionConfig {
+                        url: String::from("http://nas:9091/transmission/rpc"),
+                        user: String::from("admin"),
+                        password: String::from("456"),
+                    })
+                }],
                 res_api: ResApi::Dmhy,
                 proxy: Some(Proxy {
                     scheme: String::from("http://127.0.0.1:10080"),
@@ -366,7 +770,19 @@ mod tests {
                     dry_run: true,
                     notify: Some(Notify::Ntfy {
                         topic: "c".to_string()
-                    })
+                    }),
+                    min_confidence: 60,
+                    verify_crc32: true,
+                    subtitle_langs: vec![SubtitleLang {
+                        lang: "zh".to_string(),
+                        suffixes: vec!["jpsc".to_string()],
+                        hints: vec!["简日".to_string()]
+                    }],
+                    default_subtitle_lang: Some("zh".to_string()),
+                    collect_fonts: true,
+                    file_name_template: Some(
+                        "{name} S{season}E{episode} [{resolution}]".to_string()
+                    )
                 }),
                 collections: vec![Collection {
                     torrent_url: "u".to_string(),
@@ -382,8 +798,58 @@ mod tests {
                         match_and_replace: true,
                         matcher: Matcher::Off
                     }],
-                    external_subtitle: true
-                }]
+                    external_subtitle: true,
+                    episode_regex: None,
+                    season_regex: Some(r"S(\d+)".to_string()),
+                    title_rewrite: vec![],
+                    extras: ExtrasMode::Folders,
+                    include: vec![],
+                    exclude: vec!["CDs/**".to_string(), "Scans/**".to_string()],
+                    downloader: Some("nas".to_string()),
+                    id: None,
+                }],
+                subtitle_packs: vec![SubtitlePack {
+                    torrent_url: "s".to_string(),
+                    name: "n".to_string(),
+                    title: "2".to_string(),
+                    season: Some(1),
+                    ep_revise: 0,
+                    episode_regex: None,
+                    downloader: None,
+                    id: None,
+                }],
+                seeding: Some(Seeding {
+                    seed_ratio: Some(2.0),
+                    seed_days: Some(14),
+                    remove_after_link: true,
+                    delete_data: false,
+                    delete_unlabelled_data: false,
+                }),
+                trackers: Some(Trackers {
+                    list: vec!["udp://tracker.opentrackr.org:1337/announce".to_string()],
+                    file: None,
+                    url: Some(
+                        "https://ngosang.github.io/trackerslist/trackers_best.txt".to_string()
+                    ),
+                    refresh_hours: Some(12),
+                    max_peers: Some(5)
+                }),
+                server: Some(Server {
+                    bind: Some("127.0.0.1".to_string()),
+                    port: Some(3001),
+                    token: Some("t".to_string()),
+                    basic_auth: Some(BasicAuth {
+                        user: "u".to_string(),
+                        password: "p".to_string()
+                    }),
+                    allowed_origins: vec!["http://localhost:5173".to_string()],
+                    tls: Some(Tls {
+                        cert: "cert.pem".to_string(),
+                        key: "key.pem".to_string()
+                    })
+                }),
+                files: vec![],
+                overridden: vec![],
             }
         );
     }
@@ -413,11 +879,18 @@ mod tests {
                     user: String::from("admin"),
                     password: String::from("123123"),
                 }),
+                downloaders: vec![],
                 res_api: ResApi::Dmhy,
                 proxy: None,
                 check_interval: 10,
                 link: None,
-                collections: vec![]
+                collections: vec![],
+                subtitle_packs: vec![],
+                seeding: None,
+                trackers: None,
+                server: None,
+                files: vec![],
+                overridden: vec![],
             }
         );
     }
@@ -432,11 +905,18 @@ mod tests {
                 user: String::from("admin"),
                 password: String::from("123123"),
             }),
+            downloaders: vec![],
             res_api: ResApi::Dmhy,
             proxy: None,
             check_interval: 10,
             link: None,
             collections: vec![],
+            subtitle_packs: vec![],
+            seeding: None,
+            trackers: None,
+            server: None,
+            files: vec![],
+            overridden: vec![],
         };
         config
             .add_mikan(Mikan {
@@ -454,6 +934,12 @@ mod tests {
                 external_subtitle: true,
                 ep_revise: -2,
                 season: Some(2),
+                episode_regex: None,
+                season_regex: None,
+                title_rewrite: vec![],
+                tag_filter: vec![],
+                downloader: None,
+                id: None,
             })
             .unwrap();
 
@@ -482,4 +968,120 @@ mod tests {
         .unwrap();
         assert_eq!(config, expected_config);
     }
+
+    #[test]
+    fn test_save_preserves_format() {
+        let original = r#"# 全局设置
+check_interval = 1800 # 检查间隔(秒)
+res_api = "dmhy"
+
+[downloader]
+type = "transmission"
+url = "https://192.168.1.1:8080/transmission/rpc"
+user = "admin"
+password = "123000"
+
+# 我推的孩子
+[[mikan]]
+url = "https://mikanani.me/RSS/Bangumi?bangumiId=2995"
+name = "【我推的孩子】"
+title_contain = [
+    "繁日内嵌", # 只要繁体
+]
+
+[[mikan]]
+url = "https://mikanani.me/RSS/Bangumi?bangumiId=3000"
+name = "b" # 第二个
+"#;
+        let mut config = Config::parse(original).unwrap();
+
+        // 没有修改时原样保存
+        assert_eq!(config.to_toml(Some(original)).unwrap(), original);
+
+        // 添加后再删除, 回到原来的内容
+        config
+            .add_mikan(Mikan {
+                url: "https://mikanani.me/RSS/Bangumi?bangumiId=3001".to_string(),
+                name: "c".to_string(),
+                extra: vec![],
+                skip: vec![],
+                title_contain: vec![],
+                external_subtitle: false,
+                ep_revise: 0,
+                season: Some(0),
+                episode_regex: None,
+                season_regex: None,
+                title_rewrite: vec![],
+                tag_filter: vec![],
+                downloader: None,
+                id: None,
+            })
+            .unwrap();
+        let added = config.to_toml(Some(original)).unwrap();
+        assert!(added.starts_with(original));
+        assert_eq!(
+            &added[original.len()..],
+            "\n[[mikan]]\nurl = \"https://mikanani.me/RSS/Bangumi?bangumiId=3001\"\nname = \"c\"\nseason = 0\n"
+        );
+        assert_eq!(Config::parse(&added).unwrap(), config);
+        config
+            .rm_mikan("https://mikanani.me/RSS/Bangumi?bangumiId=3001")
+            .unwrap();
+        assert_eq!(config.to_toml(Some(&added)).unwrap(), original);
+
+        // 删除第一个, 修改第二个, 只改动这两处
+        config
+            .rm_mikan("https://mikanani.me/RSS/Bangumi?bangumiId=2995")
+            .unwrap();
+        config.mikan[0].season = Some(2);
+        config.check_interval = 600;
+        assert_eq!(
+            config.to_toml(Some(original)).unwrap(),
+            r#"# 全局设置
+check_interval = 600 # 检查间隔(秒)
+res_api = "dmhy"
+
+[downloader]
+type = "transmission"
+url = "https://192.168.1.1:8080/transmission/rpc"
+user = "admin"
+password = "123000"
+
+[[mikan]]
+url = "https://mikanani.me/RSS/Bangumi?bangumiId=3000"
+name = "b" # 第二个
+season = 2
+"#
+        );
+
+        // 原来是空数组时用 [[rules]], 内联数组中没有变化的元素保持原样, 新增的元素去掉空数组等字段
+        let original = r#"check_interval = 1800
+res_api = "dmhy"
+rules = []
+collections = [
+    { torrent_url = "a", name = "a", title = "a" },
+]
+
+[downloader]
+type = "transmission"
+url = "https://192.168.1.1:8080/transmission/rpc"
+user = "admin"
+password = "123000"
+"#;
+        let mut config = Config::parse(original).unwrap();
+        config
+            .rules
+            .push(toml::from_str("name = \"r\"\nkeywords = [\"r\"]\nres_api = \"dmhy\"").unwrap());
+        let mut collection = config.collections[0].clone();
+        collection.torrent_url = "b".to_string();
+        config.add_collection(collection).unwrap();
+        let saved = config.to_toml(Some(original)).unwrap();
+        assert!(saved.contains("[[rules]]\nname = \"r\""));
+        assert!(saved.contains("    { torrent_url = \"a\", name = \"a\", title = \"a\" },\n    { torrent_url = \"b\", name = \"a\", title = \"a\" },\n"), "{saved}");
+        assert_eq!(Config::parse(&saved).unwrap(), config);
+
+        // 原来的文件不能合并时不覆盖, 而不是丢掉注释重新生成
+        assert!(config.to_toml(Some("# 注释\ncheck_interval = [")).is_err());
+        assert!(config.to_toml(Some("")).is_ok());
+    }
 }
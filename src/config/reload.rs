@@ -0,0 +1,247 @@
+/*
+   watch 模式下监听配置文件和订阅目录, 修改后不需要重启
+   文件变化时通知 watch 的循环, 由它重新读取配置、调整检查间隔和重建 HTTP 客户端
+*/
+use std::path::{Path, PathBuf};
+
+use ::notify::{recommended_watcher, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use color_eyre::eyre::Result;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use super::{edit, split::DIRS, Config, Subscription};
+
+/**
+ * 配置文件和订阅目录中的 .toml 文件有变化时收到通知, 被 drop 后停止监听
+ * 配置文件所在的目录不递归监听, 按文件名过滤, 只递归监听 mikan.d 等订阅目录
+ */
+pub struct ConfigWatcher {
+    watcher: RecommendedWatcher,
+    dirs: Vec<PathBuf>,
+    /// 已经在监听的订阅目录
+    watching: Vec<PathBuf>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Result<(ConfigWatcher, UnboundedReceiver<()>)> {
+        // 相对路径时 parent 为 "", 而事件中的路径都是绝对路径
+        let path = path.canonicalize()?;
+        let base = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let dirs: Vec<PathBuf> = DIRS.iter().map(|dir| base.join(dir)).collect();
+        let (tx, rx) = unbounded_channel();
+        let (event_base, event_dirs) = (base.clone(), dirs.clone());
+        let mut watcher = recommended_watcher(move |event: ::notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            // 保存时先写 .tmp 再重命名, 只关心配置文件, 订阅目录本身和其中的 .toml 文件
+            let relevant = event.paths.iter().any(|p| {
+                (p.parent() == Some(event_base.as_path()) && p.file_name() == file_name.as_deref())
+                    || event_dirs.contains(p)
+                    || (p.extension().is_some_and(|ext| ext == "toml")
+                        && p.parent()
+                            .is_some_and(|parent| event_dirs.iter().any(|d| d == parent)))
+            });
+            if relevant
+                && (event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove())
+            {
+                let _ = tx.send(());
+            }
+        })?;
+        // 编辑器保存时替换文件也能收到, 订阅目录的创建和删除也在这里收到
+        watcher.watch(&base, RecursiveMode::NonRecursive)?;
+        let mut config_watcher = ConfigWatcher {
+            watcher,
+            dirs,
+            watching: vec![],
+        };
+        config_watcher.watch_new_dirs()?;
+        Ok((config_watcher, rx))
+    }
+
+    /// 启动后才创建的订阅目录, 收到通知后调用
+    pub fn watch_new_dirs(&mut self) -> Result<()> {
+        // 删除的目录 inotify 会自动移除监听
+        self.watching.retain(|dir| dir.is_dir());
+        for dir in &self.dirs {
+            if dir.is_dir() && !self.watching.contains(dir) {
+                self.watcher.watch(dir, RecursiveMode::Recursive)?;
+                self.watching.push(dir.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 两个配置之间的变化, 用于日志, 密码等不显示具体的值
+pub fn changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = vec![];
+    subscription_changes("mikan", &old.mikan, &new.mikan, &mut changes);
+    subscription_changes(
+        "collections",
+        &old.collections,
+        &new.collections,
+        &mut changes,
+    );
+    subscription_changes("rules", &old.rules, &new.rules, &mut changes);
+    subscription_changes(
+        "subtitle_packs",
+        &old.subtitle_packs,
+        &new.subtitle_packs,
+        &mut changes,
+    );
+
+    let settings = |config: &Config| {
+        let mut config = config.clone();
+        config.mikan.clear();
+        config.collections.clear();
+        config.rules.clear();
+        config.subtitle_packs.clear();
+        toml::Value::try_from(config).ok()
+    };
+    let (Some(old), Some(new)) = (settings(old), settings(new)) else {
+        return changes;
+    };
+    let mut paths = vec![];
+    edit::diff(&old, &new, &mut vec![], &mut paths);
+    for path in paths {
+        let keys: Vec<String> = path.split('.').map(String::from).collect();
+        let show = |value: &toml::Value| match edit::lookup(value, &keys) {
+            None => "无".to_string(),
+            Some(_) if is_secret(&keys) => "***".to_string(),
+            Some(v) => v.to_string(),
+        };
+        changes.push(format!("{path}: {} -> {}", show(&old), show(&new)));
+    }
+    changes
+}
+
+fn subscription_changes<T: Subscription + PartialEq>(
+    field: &str,
+    old: &[T],
+    new: &[T],
+    changes: &mut Vec<String>,
+) {
+    for item in new {
+        match old.iter().find(|o| o.key() == item.key()) {
+            None => changes.push(format!("{field}: 新增 {}", item.name())),
+            Some(o) if o != item => changes.push(format!("{field}: 修改 {}", item.name())),
+            _ => {}
+        }
+    }
+    for item in old {
+        if !new.iter().any(|n| n.key() == item.key()) {
+            changes.push(format!("{field}: 删除 {}", item.name()));
+        }
+    }
+}
+
+fn is_secret(keys: &[String]) -> bool {
+    keys.iter()
+        .any(|k| ["password", "token"].contains(&k.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Downloader, Proxy};
+    use std::{fs, time::Duration};
+
+    /// 5秒内收到通知
+    async fn next(changed: &mut UnboundedReceiver<()>) -> bool {
+        tokio::time::timeout(Duration::from_secs(5), changed.recv())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_watch_relative_path() {
+        let dir = std::env::temp_dir().join(format!("muuf-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("muuf.toml");
+        fs::write(&path, "check_interval = 10\n").unwrap();
+        // 相对于当前目录的路径, 如 --config muuf.toml
+        let cwd = std::env::current_dir().unwrap();
+        let depth = cwd.components().count() - 1;
+        let relative = PathBuf::from("../".repeat(depth)).join(path.strip_prefix("/").unwrap());
+        assert!(relative.is_relative());
+
+        let (mut watcher, mut changed) = ConfigWatcher::new(&relative).unwrap();
+        // 同目录中的其他文件不会通知
+        fs::write(dir.join("other.toml"), "").unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), changed.recv())
+                .await
+                .is_err()
+        );
+        fs::write(&path, "check_interval = 20\n").unwrap();
+        assert!(next(&mut changed).await);
+        while changed.try_recv().is_ok() {}
+
+        // 启动后创建的订阅目录
+        fs::create_dir(dir.join("mikan.d")).unwrap();
+        assert!(next(&mut changed).await);
+        watcher.watch_new_dirs().unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        while changed.try_recv().is_ok() {}
+        fs::write(dir.join("mikan.d").join("a.toml"), "").unwrap();
+        assert!(next(&mut changed).await);
+
+        drop(watcher);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_changes() {
+        let old = Config::parse(
+            r#"
+            check_interval = 1800
+            res_api = "dmhy"
+
+            [downloader]
+            type = "transmission"
+            url = "http://localhost:9091/transmission/rpc"
+            user = "admin"
+            password = "123123"
+
+            [[mikan]]
+            url = "https://mikanani.me/RSS/Bangumi?bangumiId=1"
+            name = "a"
+
+            [[mikan]]
+            url = "https://mikanani.me/RSS/Bangumi?bangumiId=2"
+            name = "b"
+            "#,
+        )
+        .unwrap();
+        assert!(changes(&old, &old).is_empty());
+
+        let mut new = old.clone();
+        new.check_interval = 600;
+        new.proxy = Some(Proxy {
+            scheme: "socks5://127.0.0.1:1080".to_string(),
+            username: None,
+            password: None,
+        });
+        let Downloader::Transmission(t) = &mut new.downloader;
+        t.password = "secret".to_string();
+        new.mikan.remove(0);
+        new.mikan[0].season = Some(2);
+        let mut added = new.mikan[0].clone();
+        added.url = "https://mikanani.me/RSS/Bangumi?bangumiId=3".to_string();
+        added.name = "c".to_string();
+        new.mikan.push(added);
+
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                "mikan: 修改 b",
+                "mikan: 新增 c",
+                "mikan: 删除 a",
+                "check_interval: 1800 -> 600",
+                "downloader.password: *** -> ***",
+                "proxy: 无 -> { scheme = \"socks5://127.0.0.1:1080\" }",
+            ]
+        );
+    }
+}
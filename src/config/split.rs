@@ -0,0 +1,532 @@
+/*
+   订阅可以放在配置文件旁边的目录中, 每个文件一个订阅, 方便版本管理和分享
+   mikan.d        Mikan
+   collections.d  Collection
+   rules.d        Rule
+   subtitle_packs.d  SubtitlePack
+   目录中所有 .toml 文件按文件名排序, 排在配置文件中的订阅后面
+   文件的内容和 muuf.toml 中的 [[mikan]] 一样, 只是没有表头
+   保存时订阅按id写回读取时的文件, 修改名字或 url 也不换文件, 新增的订阅在目录存在时写到目录中的新文件
+   目录中的订阅和其他订阅的 key 重复时读取失败
+*/
+use std::{
+    fs, iter,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use color_eyre::eyre::{eyre, Result};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{edit, write_atomic, Collection, Config, Mikan, Rule, SubtitlePack};
+
+pub const MIKAN_DIR: &str = "mikan.d";
+pub const COLLECTIONS_DIR: &str = "collections.d";
+pub const RULES_DIR: &str = "rules.d";
+pub const SUBTITLE_PACKS_DIR: &str = "subtitle_packs.d";
+pub(super) const DIRS: [&str; 4] = [MIKAN_DIR, COLLECTIONS_DIR, RULES_DIR, SUBTITLE_PACKS_DIR];
+
+/// 订阅以 key 区分, 如 mikan 的 url
+pub trait Subscription: Serialize + DeserializeOwned + JsonSchema + Clone {
+    fn key(&self) -> &str;
+    fn name(&self) -> &str;
+    /// API 中使用的id, 修改 key 后不变
+    fn id(&self) -> Option<&str>;
+    fn id_mut(&mut self) -> &mut Option<String>;
+}
+
+impl Subscription for Mikan {
+    fn key(&self) -> &str {
+        &self.url
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    fn id_mut(&mut self) -> &mut Option<String> {
+        &mut self.id
+    }
+}
+
+impl Subscription for Collection {
+    fn key(&self) -> &str {
+        &self.torrent_url
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    fn id_mut(&mut self) -> &mut Option<String> {
+        &mut self.id
+    }
+}
+
+impl Subscription for Rule {
+    fn key(&self) -> &str {
+        &self.name
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    fn id_mut(&mut self) -> &mut Option<String> {
+        &mut self.id
+    }
+}
+
+impl Subscription for SubtitlePack {
+    fn key(&self) -> &str {
+        &self.torrent_url
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    fn id_mut(&mut self) -> &mut Option<String> {
+        &mut self.id
+    }
+}
+
+/// id 为 id 的订阅来自哪个文件, path 为 None 时来自配置文件
+#[derive(Debug, Clone)]
+pub struct SubscriptionFile {
+    dir: &'static str,
+    id: String,
+    path: Option<PathBuf>,
+}
+
+/// 配置文件和订阅目录中所有文件的内容
+pub struct ConfigFiles {
+    main: String,
+    subscriptions: Vec<(&'static str, PathBuf, String)>,
+}
+
+/// 所有配置文件的修改时间, 有变化时重新读取
+pub type Stamp = Vec<(PathBuf, Option<SystemTime>)>;
+
+fn dir_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml") && path.is_file())
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+fn base_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+impl ConfigFiles {
+    pub fn read(path: &Path) -> Result<Self> {
+        let main = fs::read_to_string(path)?;
+        let mut subscriptions = vec![];
+        for dir in DIRS {
+            for file in dir_files(&base_dir(path).join(dir)) {
+                let content = fs::read_to_string(&file)?;
+                subscriptions.push((dir, file, content));
+            }
+        }
+        Ok(ConfigFiles {
+            main,
+            subscriptions,
+        })
+    }
+
+    /// 所有文件的内容和路径的CRC32
+    pub fn revision(&self) -> String {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(self.main.as_bytes());
+        for (_, path, content) in &self.subscriptions {
+            hasher.update(path.to_string_lossy().as_bytes());
+            hasher.update(content.as_bytes());
+        }
+        format!("{:08x}", hasher.finalize())
+    }
+
+    pub fn stamp(path: &Path) -> Stamp {
+        let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+        let mut stamp = vec![(path.to_path_buf(), modified(path))];
+        for dir in DIRS {
+            let dir = base_dir(path).join(dir);
+            stamp.push((dir.clone(), modified(&dir)));
+            for file in dir_files(&dir) {
+                let m = modified(&file);
+                stamp.push((file, m));
+            }
+        }
+        stamp
+    }
+
+    pub fn into_config(self) -> Result<Config> {
+        let mut config = self.into_uncompiled()?;
+        config.compile()?;
+        Ok(config)
+    }
+
+    pub fn into_uncompiled(self) -> Result<Config> {
+        let mut config: Config = toml::from_str(&self.main)?;
+        let mut paths = vec![];
+        for (dir, path, content) in self.subscriptions {
+            match dir {
+                MIKAN_DIR => push(&mut config.mikan, &path, &content)?,
+                COLLECTIONS_DIR => push(&mut config.collections, &path, &content)?,
+                RULES_DIR => push(&mut config.rules, &path, &content)?,
+                _ => push(&mut config.subtitle_packs, &path, &content)?,
+            }
+            paths.push((dir, path));
+        }
+        super::env::apply(&mut config)?;
+        assign_ids(&mut config.mikan);
+        assign_ids(&mut config.collections);
+        assign_ids(&mut config.rules);
+        assign_ids(&mut config.subtitle_packs);
+        config.files = [
+            sources(MIKAN_DIR, &config.mikan, &paths),
+            sources(COLLECTIONS_DIR, &config.collections, &paths),
+            sources(RULES_DIR, &config.rules, &paths),
+            sources(SUBTITLE_PACKS_DIR, &config.subtitle_packs, &paths),
+        ]
+        .concat();
+        Ok(config)
+    }
+}
+
+/// 目录中的订阅排在配置文件中的订阅后面, 按顺序对应读取的文件
+fn sources<T: Subscription>(
+    dir: &'static str,
+    items: &[T],
+    paths: &[(&'static str, PathBuf)],
+) -> Vec<SubscriptionFile> {
+    let paths = paths
+        .iter()
+        .filter(|(d, _)| *d == dir)
+        .map(|(_, path)| Some(path.clone()))
+        .collect::<Vec<_>>();
+    iter::repeat_n(None, items.len() - paths.len())
+        .chain(paths)
+        .zip(items)
+        .map(|(path, item)| SubscriptionFile {
+            dir,
+            id: item.id().unwrap_or_default().to_string(),
+            path,
+        })
+        .collect()
+}
+
+/// 按 key 算出的id, 手动添加的订阅加载时使用, 和它相同的id不需要保存
+fn derived_id<T: Subscription>(item: &T) -> u32 {
+    crc32fast::hash(item.key().as_bytes())
+}
+
+/**
+ * 加载时给没有id或者和前面的订阅id重复(如复制的订阅文件)的订阅生成id, 只在内存中, 不写文件
+ * 同样的配置每次加载生成的id相同, 和其他id冲突时依次加一
+ */
+pub(super) fn assign_ids<T: Subscription>(items: &mut [T]) {
+    for i in 0..items.len() {
+        let id = items[i].id();
+        let duplicated = id.is_some() && items[..i].iter().any(|other| other.id() == id);
+        if id.is_some() && !duplicated {
+            continue;
+        }
+        let derived = derived_id(&items[i]);
+        let id = (0..)
+            .map(|n| format!("{:08x}", derived.wrapping_add(n)))
+            .find(|id| !items.iter().any(|item| item.id() == Some(id)))
+            .unwrap_or_default();
+        *items[i].id_mut() = Some(id);
+    }
+}
+
+/// 保存前去掉和 key 算出的一样的id, 下次加载时会重新生成
+fn strip_derived_id<T: Subscription>(item: &mut T) {
+    if item.id() == Some(&format!("{:08x}", derived_id(item))) {
+        *item.id_mut() = None;
+    }
+}
+
+/// 同一个订阅写在两个地方时保存只会保留一个, 读取时报错让用户自己删除
+fn push<T: Subscription>(items: &mut Vec<T>, path: &Path, content: &str) -> Result<()> {
+    let item: T = toml::from_str(content).map_err(|e| eyre!("{}: {e}", path.display()))?;
+    if items.iter().any(|other| other.key() == item.key()) {
+        return Err(eyre!(
+            "{}: 和前面的订阅重复: {}",
+            path.display(),
+            item.key()
+        ));
+    }
+    items.push(item);
+    Ok(())
+}
+
+impl Config {
+    /// 保存到 path 和旁边的订阅目录, 返回留在 path 中的配置
+    pub(super) fn save_split(&self, path: &Path) -> Result<Config> {
+        let mut written = vec![];
+        let mut main = self.clone();
+        main.files.clear();
+        main.mikan = self.save_dir(&self.mikan, path, MIKAN_DIR, &mut written)?;
+        main.collections = self.save_dir(&self.collections, path, COLLECTIONS_DIR, &mut written)?;
+        main.rules = self.save_dir(&self.rules, path, RULES_DIR, &mut written)?;
+        main.subtitle_packs =
+            self.save_dir(&self.subtitle_packs, path, SUBTITLE_PACKS_DIR, &mut written)?;
+        // 删除的订阅
+        for path in self.files.iter().filter_map(|f| f.path.as_ref()) {
+            if !written.contains(path) {
+                match fs::remove_file(path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+        }
+        Ok(main)
+    }
+
+    fn save_dir<T: Subscription>(
+        &self,
+        items: &[T],
+        path: &Path,
+        dir: &'static str,
+        written: &mut Vec<PathBuf>,
+    ) -> Result<Vec<T>> {
+        let dir_path = base_dir(path).join(dir);
+        let mut main = vec![];
+        for item in items {
+            let file = item
+                .id()
+                .and_then(|id| self.files.iter().find(|f| f.dir == dir && f.id == id));
+            let mut item = item.clone();
+            strip_derived_id(&mut item);
+            // 原来在配置文件中的订阅留在配置文件中, id重复时后面的当作新增的订阅
+            let file_path = match file.map(|f| &f.path) {
+                Some(Some(path)) if !written.contains(path) => path.clone(),
+                Some(None) => {
+                    main.push(item);
+                    continue;
+                }
+                _ if dir_path.is_dir() => self.new_file_path(&dir_path, &item, items, written),
+                _ => {
+                    main.push(item);
+                    continue;
+                }
+            };
+            let original = fs::read_to_string(&file_path).ok();
+            // 新文件也去掉可以省略的字段
+            let content = edit::to_toml(&item, Some(original.as_deref().unwrap_or_default()))?;
+            if original.as_deref() != Some(content.as_str()) {
+                write_atomic(&file_path, &content)?;
+            }
+            written.push(file_path);
+        }
+        Ok(main)
+    }
+
+    /// 用订阅的名字作为文件名, 可以覆盖已经没有对应订阅的旧文件
+    fn new_file_path<T: Subscription>(
+        &self,
+        dir_path: &Path,
+        item: &T,
+        items: &[T],
+        written: &[PathBuf],
+    ) -> PathBuf {
+        let stem = file_stem(item.name());
+        (1..)
+            .map(|i| match i {
+                1 => dir_path.join(format!("{stem}.toml")),
+                i => dir_path.join(format!("{stem}-{i}.toml")),
+            })
+            .find(|candidate| {
+                let own = self
+                    .files
+                    .iter()
+                    .find(|f| f.path.as_ref() == Some(candidate));
+                let in_use =
+                    own.is_some_and(|f| items.iter().any(|i| i.id() == Some(f.id.as_str())));
+                !written.contains(candidate) && !in_use && (own.is_some() || !candidate.exists())
+            })
+            .unwrap_or_else(|| dir_path.join(format!("{stem}.toml")))
+    }
+}
+
+/// 去掉文件名中不能用的字符
+fn file_stem(name: &str) -> String {
+    let stem = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    let stem = stem.trim().trim_start_matches('.');
+    match stem.is_empty() {
+        true => "subscription".to_string(),
+        false => stem.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_config() {
+        let dir = std::env::temp_dir().join(format!("muuf-split-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("muuf.toml");
+        fs::write(
+            &path,
+            r#"check_interval = 10
+res_api = "dmhy"
+
+[downloader]
+type = "transmission"
+url = "http://localhost:9091/transmission/rpc"
+user = "admin"
+password = "123123"
+
+[[mikan]]
+url = "https://mikanani.me/RSS/Bangumi?bangumiId=1"
+name = "main"
+"#,
+        )
+        .unwrap();
+        fs::create_dir(dir.join(MIKAN_DIR)).unwrap();
+        let show = dir.join(MIKAN_DIR).join("show.toml");
+        fs::write(
+            &show,
+            "# 一部番\nurl = \"https://mikanani.me/RSS/Bangumi?bangumiId=2\"\nname = \"show\"\n",
+        )
+        .unwrap();
+
+        let mut config = Config::load_from(&path).unwrap();
+        assert_eq!(
+            config
+                .mikan
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["main", "show"]
+        );
+
+        // 修改目录中的订阅只改动它的文件
+        let main_before = fs::read_to_string(&path).unwrap();
+        config.mikan[1].season = Some(2);
+        // 新增的订阅写到目录中
+        let mut added = config.mikan[1].clone();
+        added.url = "https://mikanani.me/RSS/Bangumi?bangumiId=3".to_string();
+        added.name = "a/b".to_string();
+        added.id = None;
+        config.add_mikan(added).unwrap();
+        config.save_to(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), main_before);
+        assert_eq!(
+            fs::read_to_string(&show).unwrap(),
+            "# 一部番\nurl = \"https://mikanani.me/RSS/Bangumi?bangumiId=2\"\nname = \"show\"\nseason = 2\n"
+        );
+        let added = dir.join(MIKAN_DIR).join("a_b.toml");
+        assert!(added.exists());
+
+        let mut config = Config::load_from(&path).unwrap();
+        assert_eq!(config.mikan.len(), 3);
+        // 删除的订阅删除文件
+        config
+            .rm_mikan("https://mikanani.me/RSS/Bangumi?bangumiId=3")
+            .unwrap();
+        config.save_to(&path).unwrap();
+        assert!(!added.exists());
+
+        // 修改 url 和名字后还写回原来的文件
+        let mut config = Config::load_from(&path).unwrap();
+        assert_eq!(config.mikan.len(), 2);
+        config.mikan[1].url = "https://mikanani.me/RSS/Bangumi?bangumiId=4".to_string();
+        config.mikan[1].name = "renamed".to_string();
+        config.save_to(&path).unwrap();
+        assert_eq!(fs::read_dir(dir.join(MIKAN_DIR)).unwrap().count(), 1);
+        let content = fs::read_to_string(&show).unwrap();
+        assert!(content.contains("bangumiId=4") && content.contains("renamed"));
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.mikan[1].name, "renamed");
+
+        // 目录中的订阅和配置文件中的重复时读取失败, 不删除文件
+        let copy = dir.join(MIKAN_DIR).join("copy.toml");
+        fs::write(
+            &copy,
+            "url = \"https://mikanani.me/RSS/Bangumi?bangumiId=1\"\nname = \"copy\"\n",
+        )
+        .unwrap();
+        let e = Config::load_from(&path).unwrap_err();
+        assert!(e.to_string().contains("copy.toml"), "{e}");
+        assert!(copy.exists() && show.exists());
+        fs::remove_file(&copy).unwrap();
+
+        // 字幕包目录
+        fs::create_dir(dir.join(SUBTITLE_PACKS_DIR)).unwrap();
+        fs::write(
+            dir.join(SUBTITLE_PACKS_DIR).join("pack.toml"),
+            "torrent_url = \"https://example.com/pack.torrent\"\nname = \"pack\"\ntitle = \"pack\"\n",
+        )
+        .unwrap();
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.subtitle_packs[0].name, "pack");
+        config.save_to(&path).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("pack"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_assign_ids() {
+        let rule = |name: &str| -> Rule {
+            toml::from_str(&format!(
+                "name = \"{name}\"\nkeywords = [\"{name}\"]\nres_api = \"dmhy\""
+            ))
+            .unwrap()
+        };
+        let mut rules = vec![rule("a"), rule("b"), rule("c")];
+        rules[0].id = Some("1".to_string());
+        // 复制的订阅文件中id重复
+        rules[1].id = Some("1".to_string());
+        assign_ids(&mut rules);
+        assert_eq!(rules[0].id(), Some("1"));
+        let ids = rules
+            .iter()
+            .map(|r| r.id().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert!(ids[1] != "1" && ids[2] != "1" && ids[1] != ids[2]);
+        // 每次加载生成的id相同
+        let mut again = vec![rule("a"), rule("b"), rule("c")];
+        again[0].id = Some("1".to_string());
+        again[1].id = Some("1".to_string());
+        assign_ids(&mut again);
+        assert_eq!(again, rules);
+
+        // 按 key 算出的id不保存, 修改 key 后需要保存
+        again.iter_mut().for_each(strip_derived_id);
+        assert_eq!(again[0].id(), Some("1"));
+        assert_eq!(again[1].id(), None);
+        assert_eq!(again[2].id(), None);
+        rules[2].name = "d".to_string();
+        strip_derived_id(&mut rules[2]);
+        assert_eq!(rules[2].id(), Some(ids[2].as_str()));
+    }
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(file_stem("【我推的孩子】"), "【我推的孩子】");
+        assert_eq!(file_stem("a/b: c?"), "a_b_ c_");
+        assert_eq!(file_stem(" .. "), "subscription");
+    }
+}
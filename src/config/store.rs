@@ -0,0 +1,201 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use color_eyre::eyre::{eyre, Result};
+
+use super::{
+    split::{ConfigFiles, Stamp},
+    Config,
+};
+
+/**
+ * 进程内共享的配置, 所有修改都在写锁中基于最新的配置进行, 同时修改时不会丢失
+ * 读取时发现配置文件或订阅目录被外部修改过(手动编辑或者 watch 之外的进程)会重新加载
+ */
+pub static CONFIG_STORE: LazyLock<ConfigStore> = LazyLock::new(|| ConfigStore::new(Config::path()));
+
+pub struct ConfigStore {
+    path: PathBuf,
+    current: RwLock<Option<Snapshot>>,
+}
+
+#[derive(Clone)]
+struct Snapshot {
+    config: Arc<Config>,
+    /// 所有配置文件内容的CRC32, API 中用作 ETag
+    revision: String,
+    stamp: Stamp,
+}
+
+/// 修改时带的 revision 已经过期
+#[derive(Debug)]
+pub struct RevisionMismatch {
+    pub current: String,
+}
+
+impl fmt::Display for RevisionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "配置已经被修改, 当前版本为{}", self.current)
+    }
+}
+
+impl std::error::Error for RevisionMismatch {}
+
+impl ConfigStore {
+    pub fn new(path: PathBuf) -> Self {
+        ConfigStore {
+            path,
+            current: RwLock::new(None),
+        }
+    }
+
+    fn stamp(&self) -> Stamp {
+        ConfigFiles::stamp(&self.path)
+    }
+
+    fn read_files(&self) -> Result<Snapshot> {
+        let stamp = self.stamp();
+        let files = ConfigFiles::read(&self.path)?;
+        Ok(Snapshot {
+            revision: files.revision(),
+            config: Arc::new(files.into_config()?),
+            stamp,
+        })
+    }
+
+    /// 返回没有过期的快照, 需要时重新读取文件
+    fn fresh(&self, current: &mut Option<Snapshot>) -> Result<Snapshot> {
+        match current {
+            Some(snapshot) if snapshot.stamp == self.stamp() => Ok(snapshot.clone()),
+            _ => {
+                let snapshot = self.read_files()?;
+                *current = Some(snapshot.clone());
+                Ok(snapshot)
+            }
+        }
+    }
+
+    /// 当前的配置和 revision
+    pub fn get(&self) -> Result<(Arc<Config>, String)> {
+        {
+            let current = self
+                .current
+                .read()
+                .map_err(|_| eyre!("config lock poisoned"))?;
+            if let Some(snapshot) = current.as_ref() {
+                if snapshot.stamp == self.stamp() {
+                    return Ok((snapshot.config.clone(), snapshot.revision.clone()));
+                }
+            }
+        }
+        let mut current = self
+            .current
+            .write()
+            .map_err(|_| eyre!("config lock poisoned"))?;
+        let snapshot = self.fresh(&mut current)?;
+        Ok((snapshot.config, snapshot.revision))
+    }
+
+    /**
+     * 在写锁中修改配置并保存, 返回 f 的结果和新的 revision
+     * expected 不为 None 时必须和当前的 revision 一致, 否则返回 RevisionMismatch
+     * f 返回错误时不会保存
+     */
+    pub fn update<T>(
+        &self,
+        expected: Option<&str>,
+        f: impl FnOnce(&mut Config) -> Result<T>,
+    ) -> Result<(T, String)> {
+        let mut current = self
+            .current
+            .write()
+            .map_err(|_| eyre!("config lock poisoned"))?;
+        let snapshot = self.fresh(&mut current)?;
+        if expected.is_some_and(|expected| expected != snapshot.revision) {
+            return Err(RevisionMismatch {
+                current: snapshot.revision,
+            }
+            .into());
+        }
+        let mut config = Config::clone(&snapshot.config);
+        let result = f(&mut config)?;
+        config.save_to(&self.path)?;
+        let snapshot = self.read_files()?;
+        let new_revision = snapshot.revision.clone();
+        *current = Some(snapshot);
+        Ok((result, new_revision))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Mikan;
+    use std::fs;
+
+    fn mikan(url: &str) -> Mikan {
+        toml::from_str(&format!("name = \"m\"\nurl = \"{url}\"")).unwrap()
+    }
+
+    #[test]
+    fn test_config_store() {
+        let dir = std::env::temp_dir().join(format!("muuf-store-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("muuf.toml");
+        fs::write(
+            &path,
+            r#"
+            check_interval = 10
+            res_api = "dmhy"
+
+            [downloader]
+            type = "transmission"
+            url = "http://localhost:9091/transmission/rpc"
+            user = "admin"
+            password = "123123"
+            "#,
+        )
+        .unwrap();
+
+        let store = ConfigStore::new(path.clone());
+        let (config, rev) = store.get().unwrap();
+        assert!(config.mikan.is_empty());
+
+        let ((), rev2) = store
+            .update(Some(&rev), |config| config.add_mikan(mikan("a")))
+            .unwrap();
+        assert_ne!(rev, rev2);
+        // 过期的 revision 不能修改
+        let err = store
+            .update(Some(&rev), |config| config.add_mikan(mikan("b")))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RevisionMismatch>().unwrap().current,
+            rev2
+        );
+        // 不带 revision 时在最新的配置上修改
+        store
+            .update(None, |config| config.add_mikan(mikan("b")))
+            .unwrap();
+
+        let (config, rev3) = store.get().unwrap();
+        assert_eq!(config.mikan.len(), 2);
+        assert_eq!(
+            Config::parse(&fs::read_to_string(&path).unwrap())
+                .unwrap()
+                .mikan
+                .len(),
+            2
+        );
+        assert!(!dir.join("muuf.toml.tmp").exists());
+
+        // f 出错时不保存
+        assert!(store.update(None, |config| config.rm_mikan("c")).is_err());
+        assert_eq!(store.get().unwrap().1, rev3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
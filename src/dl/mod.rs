@@ -3,17 +3,63 @@ use color_eyre::eyre::{eyre, Result};
 use reqwest::Url;
 use transmission_rpc::{
     types::{
-        BasicAuth, Id, RpcResponse, TorrentAddArgs, TorrentAddedOrDuplicate, TorrentGetField,
-        TorrentSetArgs, Torrents, TrackerList,
+        BasicAuth, Id, RpcResponse, TorrentAction, TorrentAddArgs, TorrentAddedOrDuplicate,
+        TorrentGetField, TorrentSetArgs, TorrentStatus, Torrents, TrackerList,
     },
     TransClient,
 };
 
 use crate::config::Downloader;
 
-pub fn get_client(downloader_config: &Downloader) -> impl Client {
+/// muuf 添加的种子都下载到这个文件夹中
+pub const DOWNLOAD_DIR: &str = "/downloads/muuf";
+/// muuf 添加的种子都有这个标签
+const MUUF_LABEL: &str = "muuf";
+
+/// 种子来自哪种订阅
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Rule,
+    Mikan,
+    Collection,
+    SubtitlePack,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Rule => "rule",
+            Kind::Mikan => "mikan",
+            Kind::Collection => "collection",
+            Kind::SubtitlePack => "subtitle_pack",
+        }
+    }
+}
+
+/// 添加种子的订阅, 决定下载的文件夹和种子的标签
+pub struct Source<'a> {
+    pub kind: Kind,
+    pub name: &'a str,
+}
+
+impl Source<'_> {
+    pub fn new(kind: Kind, name: &str) -> Source<'_> {
+        Source { kind, name }
+    }
+
+    /// 如 ["muuf", "muuf:mikan", "孤独摇滚"], Transmission 的标签中不能有逗号
+    pub fn labels(&self) -> Vec<String> {
+        vec![
+            MUUF_LABEL.to_string(),
+            format!("{MUUF_LABEL}:{}", self.kind.as_str()),
+            self.name.replace(',', " "),
+        ]
+    }
+}
+
+pub fn get_client(downloader_config: &Downloader) -> Box<dyn Client> {
     match downloader_config {
-        Downloader::Transmission(config) => Transmission {
+        Downloader::Transmission(config) => Box::new(Transmission {
             client: TransClient::with_auth(
                 Url::parse(&config.url).unwrap(),
                 BasicAuth {
@@ -21,20 +67,39 @@ pub fn get_client(downloader_config: &Downloader) -> impl Client {
                     password: config.password.to_string(),
                 },
             ),
-        },
+        }),
     }
 }
 
 #[async_trait]
 pub trait Client: Send {
-    async fn torrent_add(&mut self, magnet: String, folder: &str) -> Result<()>;
-    async fn torrent_add_by_meta(&mut self, meta: String, folder: &str) -> Result<()>;
+    async fn torrent_add(&mut self, magnet: String, source: &Source) -> Result<()>;
+    async fn torrent_add_by_meta(&mut self, meta: String, source: &Source) -> Result<()> {
+        self.torrent_add_by_meta_with_files(meta, source, &[]).await
+    }
+    /// files_unwanted 为种子中文件的序号, 这些文件不会被下载
+    async fn torrent_add_by_meta_with_files(
+        &mut self,
+        meta: String,
+        source: &Source,
+        files_unwanted: &[usize],
+    ) -> Result<()>;
+    async fn torrent_set_files_wanted(
+        &mut self,
+        torrent: &Torrent,
+        files_wanted: &[usize],
+        files_unwanted: &[usize],
+    ) -> Result<()>;
     async fn torrent_set_tracker_list(
         &mut self,
         torrents: &[&Torrent],
         tracker_list: Vec<String>,
     ) -> Result<()>;
-    async fn torrent_get(&mut self) -> Result<Vec<Torrent>>;
+    /// 只返回 muuf 添加的种子, 手动添加的种子只返回 hash
+    async fn torrent_get(&mut self) -> Result<ServerTorrents>;
+    async fn torrent_pause(&mut self, torrents: &[&Torrent]) -> Result<()>;
+    /// delete_data 为 true 时同时删除下载的文件, 已经硬链接的文件不受影响
+    async fn torrent_remove(&mut self, torrents: &[&Torrent], delete_data: bool) -> Result<()>;
 }
 
 #[derive(Debug)]
@@ -46,6 +111,87 @@ pub struct Torrent {
     pub percent_done: f32,
     pub torrent_file: String,
     pub trackers: Vec<String>,
+    /// 种子中每个文件是否需要下载
+    pub wanted: Vec<bool>,
+    pub labels: Vec<String>,
+    /// 种子中的文件, 相对于 download_dir
+    pub files: Vec<String>,
+    pub paused: bool,
+    pub upload_ratio: f32,
+    /// 做种的秒数
+    pub seconds_seeding: i64,
+    /// 上传的字节数
+    pub uploaded: i64,
+    /// 连接的peer数
+    pub peers_connected: i64,
+}
+
+#[cfg(test)]
+impl Torrent {
+    /// 测试用, muuf 添加的未完成的单文件种子 {name}.mkv, 其他字段为空
+    pub fn fixture(name: &str) -> Torrent {
+        Torrent {
+            id: 1,
+            hash: "h".to_string(),
+            name: name.to_string(),
+            download_dir: format!("{DOWNLOAD_DIR}/{name}/"),
+            percent_done: 0.0,
+            torrent_file: "".to_string(),
+            trackers: vec![],
+            wanted: vec![true],
+            labels: vec![],
+            files: vec![format!("{name}.mkv")],
+            paused: false,
+            upload_ratio: 0.0,
+            seconds_seeding: 0,
+            uploaded: 0,
+            peers_connected: 0,
+        }
+    }
+}
+
+impl Torrent {
+    /// 是否是 muuf 添加的种子, 兼容没有标签的旧版本添加的种子
+    pub fn is_managed(&self) -> bool {
+        self.is_labelled() || self.download_dir.starts_with(&format!("{DOWNLOAD_DIR}/"))
+    }
+
+    /// 有 muuf 的标签, 没有时只能按下载目录判断
+    pub fn is_labelled(&self) -> bool {
+        self.labels.iter().any(|l| l == MUUF_LABEL)
+    }
+}
+
+/**
+ * 下载器中的种子
+ * 手动添加的种子不归 muuf 管理, 做种策略、tracker 和 include/exclude 都不会处理
+ * 只记录它们的 hash, 订阅中的种子已经被手动添加时不再重复添加
+ */
+#[derive(Debug, Default)]
+pub struct ServerTorrents {
+    pub managed: Vec<Torrent>,
+    pub unmanaged_hashes: Vec<String>,
+}
+
+impl ServerTorrents {
+    pub fn new(torrents: Vec<Torrent>) -> ServerTorrents {
+        let (managed, unmanaged): (Vec<_>, Vec<_>) =
+            torrents.into_iter().partition(Torrent::is_managed);
+        ServerTorrents {
+            managed,
+            unmanaged_hashes: unmanaged.into_iter().map(|t| t.hash).collect(),
+        }
+    }
+
+    /// muuf 添加的种子
+    pub fn find(&self, hash: &str) -> Option<&Torrent> {
+        self.managed.iter().find(|t| t.hash == hash)
+    }
+
+    /// 是否已经被手动添加, 这时跳过不添加
+    pub fn is_unmanaged(&self, hash: &str) -> bool {
+        self.unmanaged_hashes.iter().any(|h| h == hash)
+    }
 }
 
 struct Transmission {
@@ -54,10 +200,11 @@ struct Transmission {
 
 #[async_trait]
 impl Client for Transmission {
-    async fn torrent_add(&mut self, magnet: String, folder: &str) -> Result<()> {
+    async fn torrent_add(&mut self, magnet: String, source: &Source) -> Result<()> {
         let add: TorrentAddArgs = TorrentAddArgs {
             filename: Some(magnet),
-            download_dir: Some(format!("/downloads/muuf/{}/", folder)),
+            download_dir: Some(format!("{DOWNLOAD_DIR}/{}/", source.name)),
+            labels: Some(source.labels()),
             ..TorrentAddArgs::default()
         };
         let resp: RpcResponse<TorrentAddedOrDuplicate> =
@@ -69,11 +216,22 @@ impl Client for Transmission {
         }
     }
 
-    async fn torrent_add_by_meta(&mut self, meta: String, folder: &str) -> Result<()> {
+    async fn torrent_add_by_meta_with_files(
+        &mut self,
+        meta: String,
+        source: &Source,
+        files_unwanted: &[usize],
+    ) -> Result<()> {
         let add: TorrentAddArgs = TorrentAddArgs {
             metainfo: Some(meta),
-            download_dir: Some(format!("/downloads/muuf/{}/", folder)),
+            download_dir: Some(format!("{DOWNLOAD_DIR}/{}/", source.name)),
+            labels: Some(source.labels()),
             paused: Some(false),
+            files_unwanted: if files_unwanted.is_empty() {
+                None
+            } else {
+                Some(files_unwanted.iter().map(|i| *i as i32).collect())
+            },
             ..TorrentAddArgs::default()
         };
         let resp: RpcResponse<TorrentAddedOrDuplicate> =
@@ -103,7 +261,34 @@ impl Client for Transmission {
         Ok(())
     }
 
-    async fn torrent_get(&mut self) -> Result<Vec<Torrent>> {
+    async fn torrent_set_files_wanted(
+        &mut self,
+        torrent: &Torrent,
+        files_wanted: &[usize],
+        files_unwanted: &[usize],
+    ) -> Result<()> {
+        let to_i32 = |files: &[usize]| {
+            if files.is_empty() {
+                None
+            } else {
+                Some(files.iter().map(|i| *i as i32).collect())
+            }
+        };
+        self.client
+            .torrent_set(
+                TorrentSetArgs {
+                    files_wanted: to_i32(files_wanted),
+                    files_unwanted: to_i32(files_unwanted),
+                    ..TorrentSetArgs::default()
+                },
+                Some(vec![Id::Id(torrent.id)]),
+            )
+            .await
+            .map_err(|e| eyre!(e))?;
+        Ok(())
+    }
+
+    async fn torrent_get(&mut self) -> Result<ServerTorrents> {
         let resp: RpcResponse<Torrents<transmission_rpc::types::Torrent>> = self
             .client
             .torrent_get(
@@ -115,6 +300,14 @@ impl Client for Transmission {
                     TorrentGetField::PercentDone,
                     TorrentGetField::TorrentFile,
                     TorrentGetField::Trackers,
+                    TorrentGetField::Wanted,
+                    TorrentGetField::Files,
+                    TorrentGetField::Status,
+                    TorrentGetField::UploadRatio,
+                    TorrentGetField::SecondsSeeding,
+                    TorrentGetField::UploadedEver,
+                    TorrentGetField::PeersConnected,
+                    TorrentGetField::Labels,
                 ]),
                 None,
             )
@@ -137,9 +330,87 @@ impl Client for Transmission {
                     .into_iter()
                     .map(|x| x.announce)
                     .collect(),
+                wanted: it
+                    .wanted
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|w| w != 0)
+                    .collect(),
+                labels: it.labels.unwrap_or_default(),
+                files: it
+                    .files
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|f| f.name)
+                    .collect(),
+                paused: it.status == Some(TorrentStatus::Stopped),
+                upload_ratio: it.upload_ratio.unwrap_or_default(),
+                seconds_seeding: it.seconds_seeding.unwrap_or_default(),
+                uploaded: it.uploaded_ever.unwrap_or_default(),
+                peers_connected: it.peers_connected.unwrap_or_default(),
             })
             .collect();
 
-        Ok(torrents)
+        Ok(ServerTorrents::new(torrents))
+    }
+
+    async fn torrent_pause(&mut self, torrents: &[&Torrent]) -> Result<()> {
+        self.client
+            .torrent_action(
+                TorrentAction::Stop,
+                torrents.iter().map(|t| Id::Id(t.id)).collect(),
+            )
+            .await
+            .map_err(|e| eyre!(e))?;
+        Ok(())
+    }
+
+    async fn torrent_remove(&mut self, torrents: &[&Torrent], delete_data: bool) -> Result<()> {
+        self.client
+            .torrent_remove(torrents.iter().map(|t| Id::Id(t.id)).collect(), delete_data)
+            .await
+            .map_err(|e| eyre!(e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_torrents() {
+        let labelled = Torrent {
+            hash: "a".to_string(),
+            download_dir: "/downloads/".to_string(),
+            labels: vec!["muuf".to_string()],
+            ..Torrent::fixture("孤独摇滚")
+        };
+        let legacy = Torrent {
+            hash: "b".to_string(),
+            ..Torrent::fixture("孤独摇滚")
+        };
+        // 手动添加的, 其他标签不算
+        let manual = Torrent {
+            hash: "c".to_string(),
+            download_dir: "/downloads/".to_string(),
+            labels: vec!["muuf:mikan".to_string()],
+            ..Torrent::fixture("孤独摇滚")
+        };
+        let torrents = ServerTorrents::new(vec![labelled, legacy, manual]);
+        assert_eq!(torrents.managed.len(), 2);
+        assert!(torrents.find("a").is_some());
+        assert!(torrents.find("b").is_some());
+        assert!(torrents.find("c").is_none());
+        assert!(torrents.is_unmanaged("c"));
+        assert!(!torrents.is_unmanaged("a"));
+    }
+
+    #[test]
+    fn test_source_labels() {
+        assert_eq!(
+            Source::new(Kind::Mikan, "Re:Zero, Starting Life").labels(),
+            vec!["muuf", "muuf:mikan", "Re:Zero  Starting Life"]
+        );
     }
 }
@@ -9,7 +9,8 @@ pub mod serve;
 
 use bytes::Bytes;
 use color_eyre::eyre::Result;
-use std::sync::LazyLock;
+use reqwest::{IntoUrl, RequestBuilder};
+use std::sync::{LazyLock, RwLock};
 
 use std::path::PathBuf;
 
@@ -19,14 +20,47 @@ use tracing_subscriber::{self, layer::SubscriberExt, util::SubscriberInitExt, La
 
 const VIDEO_EXTS: [&str; 2] = ["mp4", "mkv"];
 
-pub static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+pub static CLIENT: LazyLock<HttpClient> = LazyLock::new(|| {
+    let (config, _) = config::CONFIG_STORE.get().unwrap();
+    HttpClient {
+        client: RwLock::new(build_client(config.proxy.as_ref()).unwrap()),
+    }
+});
+
+/// 共享的 HTTP 客户端, 代理配置修改后可以重建
+pub struct HttpClient {
+    client: RwLock<reqwest::Client>,
+}
+
+impl HttpClient {
+    fn current(&self) -> reqwest::Client {
+        self.client.read().unwrap().clone()
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.current().get(url)
+    }
+
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.current().post(url)
+    }
+
+    /// 用新的代理配置重建, 出错时保留原来的客户端
+    pub fn set_proxy(&self, proxy: Option<&config::Proxy>) -> Result<()> {
+        let client = build_client(proxy)?;
+        *self.client.write().unwrap() = client;
+        Ok(())
+    }
+}
+
+fn build_client(proxy: Option<&config::Proxy>) -> Result<reqwest::Client> {
     let mut client_builder = reqwest::Client::builder();
-    if let Some(config) = config::Config::load().unwrap().proxy {
-        let proxy = reqwest::Proxy::all(config.scheme.to_string()).unwrap();
+    if let Some(config) = proxy {
+        let proxy = reqwest::Proxy::all(config.scheme.to_string())?;
         client_builder = client_builder.proxy(proxy);
     }
-    client_builder.build().unwrap()
-});
+    Ok(client_builder.build()?)
+}
 
 pub async fn get_url_bytes(url: &str) -> Result<Bytes> {
     CLIENT
@@ -89,6 +123,8 @@ pub fn initialize_logging(target: &str) -> Result<()> {
         // .with_line_number(true)
         // .with_file(true)
         // .with_writer(log_file)
+        // stdout 留给命令的输出, 如 validate --json
+        .with_writer(std::io::stderr)
         .with_target(true)
         .with_ansi(false)
         .with_filter(tracing_subscriber::filter::EnvFilter::from_default_env());
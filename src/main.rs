@@ -1,12 +1,20 @@
+use std::{path::PathBuf, sync::Arc};
+
 use clap::{Parser, Subcommand};
+use color_eyre::eyre::bail;
 use color_eyre::eyre::Result;
 use muuf::{
-    checker::{check, check_everything},
-    config::Config,
+    checker::{self, check, check_everything, preview_collection, Level, Report},
+    config::{
+        changes, schema, set_config_path, Collection, Config, ConfigWatcher, SchemaKind,
+        CONFIG_STORE,
+    },
     initialize_logging_from_crate_name,
     serve::serve,
+    CLIENT,
 };
-use tracing::info;
+use tokio::time::{Duration, Instant};
+use tracing::{error, info};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -14,40 +22,153 @@ async fn main() -> Result<()> {
     initialize_logging_from_crate_name()?;
     info!("muuf started, version: {}", env!("CARGO_PKG_VERSION"));
     let cli = Cli::parse();
+    if let Some(path) = cli.config {
+        set_config_path(path);
+    }
     match cli.commands {
-        Commands::Watch => watch().await,
-        Commands::Serve => serve().await,
+        Commands::Watch => watch().await?,
+        Commands::Serve => serve().await?,
         Commands::Check {
             collection,
             mikan,
             res,
         } => check(collection, mikan, res).await?,
-        Commands::Validate => validate(),
+        Commands::Validate { online, json } => validate(online, json).await?,
+        Commands::PreviewCollection { url, name } => preview(url, name).await?,
+        Commands::Config {
+            command: ConfigCommands::Schema { kind },
+        } => print!("{}", schema(kind)),
     }
 
     Ok(())
 }
 
-fn validate() {
-    let _ = Config::load().unwrap();
+async fn validate(online: bool, json: bool) -> Result<()> {
+    let report = match Config::load_uncompiled() {
+        Ok(config) => checker::validate(&config, online).await,
+        Err(e) => Report::load_failed(format!("{e:#}")),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for issue in &report.issues {
+            let level = match issue.level {
+                Level::Error => "错误",
+                Level::Warning => "警告",
+            };
+            println!("{level} {}: {}", issue.path, issue.message);
+        }
+    }
+    if !report.valid {
+        bail!("配置有{}个错误", report.errors);
+    }
     info!("Config is valid");
+    Ok(())
 }
 
-async fn watch() {
-    let config = Config::load().unwrap();
+async fn preview(url: String, name: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let collection = match config.collections.iter().find(|c| c.torrent_url == url) {
+        Some(c) => c.clone(),
+        None => {
+            let Some(name) = name else {
+                bail!("配置中没有这个collection, 需要指定 --name");
+            };
+            Collection {
+                torrent_url: url,
+                name,
+                ..Default::default()
+            }
+        }
+    };
+    let preview = preview_collection(&collection, &config.link).await?;
+
+    let season_folders = preview
+        .season_folders
+        .iter()
+        .map(|sf| {
+            format!(
+                "{{ folder = {}, season = {} }}",
+                toml::Value::String(sf.folder.clone()),
+                sf.season
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("season_folders = [{season_folders}]");
+    for folder in preview.ignored_folders {
+        println!("忽略文件夹: {folder}");
+    }
+    for (file, link) in preview.links {
+        println!("{link} <- {file}");
+    }
+    Ok(())
+}
+
+/**
+ * 每隔 check_interval 检查一次, 配置文件修改后立即生效
+ * 检查间隔从上次检查结束时重新计算, 代理修改后重建 HTTP 客户端, 下载器每次检查时按最新的配置创建
+ */
+async fn watch() -> Result<()> {
+    let (mut config, _) = CONFIG_STORE.get()?;
+    let (mut watcher, mut changed) = ConfigWatcher::new(&Config::path())?;
     tokio::spawn(async move {
-        let interval = config.check_interval;
         loop {
             let _ = check_everything().await;
-            tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+            let checked = Instant::now();
+            let sleep = tokio::time::sleep(Duration::from_secs(config.check_interval));
+            tokio::pin!(sleep);
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    Some(()) = changed.recv() => {
+                        // 编辑器保存时可能有多个事件
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        while changed.try_recv().is_ok() {}
+                        if let Err(e) = watcher.watch_new_dirs() {
+                            error!("无法监听订阅目录: {e}");
+                        }
+                        config = reload(config);
+                        sleep
+                            .as_mut()
+                            .reset(checked + Duration::from_secs(config.check_interval));
+                    }
+                }
+            }
         }
     });
-    serve().await;
+    serve().await
+}
+
+/// 重新读取配置, 有错误时继续使用原来的配置
+fn reload(config: Arc<Config>) -> Arc<Config> {
+    let new = match CONFIG_STORE.get() {
+        Ok((new, _)) => new,
+        Err(e) => {
+            error!("配置文件有错误, 继续使用原来的配置: {e}");
+            return config;
+        }
+    };
+    if Arc::ptr_eq(&config, &new) {
+        return config;
+    }
+    for change in changes(&config, &new) {
+        info!("配置已修改 {change}");
+    }
+    if new.proxy != config.proxy {
+        if let Err(e) = CLIENT.set_proxy(new.proxy.as_ref()) {
+            error!("代理配置有错误, 继续使用原来的代理: {e}");
+        }
+    }
+    new
 }
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Cli {
+    /// 配置文件, 默认为数据目录中的 muuf.toml, 也可以用环境变量 MUUF_CONFIG 指定
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
     #[clap(subcommand)]
     commands: Commands,
 }
@@ -67,6 +188,34 @@ enum Commands {
         #[clap(short, long)]
         res: bool,
     },
-    /// 校验
-    Validate,
+    /// 校验配置, 有错误时返回非0
+    Validate {
+        /// 同时检查能否连接下载器和访问订阅的链接
+        #[clap(long)]
+        online: bool,
+        /// 输出JSON格式的报告
+        #[clap(long)]
+        json: bool,
+    },
+    /// 预览collection的season文件夹和链接, 不会下载和链接
+    PreviewCollection {
+        /// 种子链接, 配置中已有时使用配置
+        url: String,
+        #[clap(short, long)]
+        name: Option<String>,
+    },
+    /// 配置文件相关
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// 输出配置的 JSON Schema, 用于编辑器的校验和补全
+    Schema {
+        #[clap(value_enum, default_value_t)]
+        kind: SchemaKind,
+    },
 }
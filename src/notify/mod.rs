@@ -1,9 +1,10 @@
 use color_eyre::eyre::Result;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::CLIENT;
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, Clone)]
 #[serde(tag = "type")]
 pub enum Notify {
     Ntfy { topic: String },
@@ -22,4 +23,17 @@ impl Notify {
         }
         Ok(())
     }
+
+    pub async fn crc32_mismatch(&self, file_name: &str) -> Result<()> {
+        match self {
+            Notify::Ntfy { topic } => {
+                CLIENT
+                    .post(format!("https://ntfy.sh/{}", topic))
+                    .body(format!("CRC32校验失败<{file_name}>"))
+                    .send()
+                    .await?;
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,1276 @@
+//! 真实的 dmhy / mikan 标题及期望的解析结果, 修改解析器时用来防止回归
+
+use super::*;
+
+struct Case {
+    title: &'static str,
+    sub_group: &'static str,
+    season: u8,
+    episode: u32,
+    name_en: Option<&'static str>,
+    name_zh: Option<&'static str>,
+    name_jp: Option<&'static str>,
+    sub: Option<&'static str>,
+    resolution: Option<&'static str>,
+    source: Option<&'static str>,
+}
+
+const EP_CASES: &[Case] = &[
+    Case {
+        title: "[ANi] 葬送的芙莉蓮 - 01 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 1,
+        name_en: None,
+        name_zh: Some("葬送的芙莉蓮"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[ANi] Sousou no Frieren - 葬送的芙莉蓮 - 28 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 28,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: Some("葬送的芙莉蓮"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[LoliHouse] 葬送的芙莉莲 / Sousou no Frieren - 05 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]",
+        sub_group: "LoliHouse",
+        season: 1,
+        episode: 5,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: Some("葬送的芙莉莲"),
+        name_jp: None,
+        sub: Some("简繁内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[Lilith-Raws] Kusuriya no Hitorigoto - 12 [Baha][WebDL 1080p AVC AAC][CHT]",
+        sub_group: "Lilith-Raws",
+        season: 1,
+        episode: 12,
+        name_en: Some("Kusuriya no Hitorigoto"),
+        name_zh: None,
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080p"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[喵萌奶茶屋&LoliHouse] 药屋少女的呢喃 / Kusuriya no Hitorigoto - 24 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕][END]",
+        sub_group: "喵萌奶茶屋&LoliHouse",
+        season: 1,
+        episode: 24,
+        name_en: Some("Kusuriya no Hitorigoto"),
+        name_zh: Some("药屋少女的呢喃"),
+        name_jp: None,
+        sub: Some("简繁日内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "【喵萌奶茶屋】★10月新番★[药屋少女的呢喃 / Kusuriya no Hitorigoto][03][1080p][简日双语][招募翻译]",
+        sub_group: "喵萌奶茶屋",
+        season: 1,
+        episode: 3,
+        name_en: Some("Kusuriya no Hitorigoto"),
+        name_zh: Some("药屋少女的呢喃"),
+        name_jp: None,
+        sub: Some("简日双语"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[桜都字幕组] 葬送的芙莉莲 / Sousou no Frieren [12][1080p][简繁内封]",
+        sub_group: "桜都字幕组",
+        season: 1,
+        episode: 12,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: Some("葬送的芙莉莲"),
+        name_jp: None,
+        sub: Some("简繁内封"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[北宇治字幕组] 药屋少女的呢喃 / Kusuriya no Hitorigoto [07][WebRip][1080p][HEVC_AAC][简日内嵌]",
+        sub_group: "北宇治字幕组",
+        season: 1,
+        episode: 7,
+        name_en: Some("Kusuriya no Hitorigoto"),
+        name_zh: Some("药屋少女的呢喃"),
+        name_jp: None,
+        sub: Some("简日内嵌"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[北宇治字幕组&霜庭云花Sub&氢气烤肉架]【我推的孩子】/【Oshi no ko】[11][Webrip][1080p][HEVC_AAC][繁日内嵌]",
+        sub_group: "北宇治字幕组&霜庭云花Sub&氢气烤肉架",
+        season: 1,
+        episode: 11,
+        name_en: Some("Oshi no ko"),
+        name_zh: Some("我推的孩子"),
+        name_jp: None,
+        sub: Some("繁日内嵌"),
+        resolution: Some("1080p"),
+        source: Some("Webrip"),
+    },
+    Case {
+        title: "[SweetSub] 迷宫饭 / Dungeon Meshi [05][WebRip][1080P][AVC 8bit][简日双语]",
+        sub_group: "SweetSub",
+        season: 1,
+        episode: 5,
+        name_en: Some("Dungeon Meshi"),
+        name_zh: Some("迷宫饭"),
+        name_jp: None,
+        sub: Some("简日双语"),
+        resolution: Some("1080P"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[SweetSub&LoliHouse] 迷宫饭 / Dungeon Meshi - 10 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕]",
+        sub_group: "SweetSub&LoliHouse",
+        season: 1,
+        episode: 10,
+        name_en: Some("Dungeon Meshi"),
+        name_zh: Some("迷宫饭"),
+        name_jp: None,
+        sub: Some("简繁日内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[猎户手抄部] 间谍过家家 第二季 / SPY×FAMILY Season 2 [37] [1080p] [简日内嵌]",
+        sub_group: "猎户手抄部",
+        season: 2,
+        episode: 37,
+        name_en: Some("SPY×FAMILY"),
+        name_zh: Some("间谍过家家"),
+        name_jp: None,
+        sub: Some("简日内嵌"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[织梦字幕组][咒术回战 第二季 Jujutsu Kaisen S2][30集][1080P][AVC][简日双语]",
+        sub_group: "织梦字幕组",
+        season: 2,
+        episode: 30,
+        name_en: Some("Jujutsu Kaisen"),
+        name_zh: Some("咒术回战"),
+        name_jp: None,
+        sub: Some("简日双语"),
+        resolution: Some("1080P"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 咒術迴戰 第二季 - 29 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 2,
+        episode: 29,
+        name_en: None,
+        name_zh: Some("咒術迴戰"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[云光字幕组] 怪兽8号 Kaijuu 8-gou [02][简体双语][1080p]招募翻译",
+        sub_group: "云光字幕组",
+        season: 1,
+        episode: 2,
+        name_en: Some("Kaijuu 8-gou"),
+        name_zh: Some("怪兽8号"),
+        name_jp: None,
+        sub: Some("简体双语"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[桜都字幕组] 坂本日常 / Sakamoto Days [01][1080p][简体内嵌]",
+        sub_group: "桜都字幕组",
+        season: 1,
+        episode: 1,
+        name_en: Some("Sakamoto Days"),
+        name_zh: Some("坂本日常"),
+        name_jp: None,
+        sub: Some("简体内嵌"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 我獨自升級 - 12 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 12,
+        name_en: None,
+        name_zh: Some("我獨自升級"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[LoliHouse] 我独自升级 / Ore dake Level Up na Ken - 03 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]",
+        sub_group: "LoliHouse",
+        season: 1,
+        episode: 3,
+        name_en: Some("Ore dake Level Up na Ken"),
+        name_zh: Some("我独自升级"),
+        name_jp: None,
+        sub: Some("简繁内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "【极影字幕社】★4月新番 天国大魔境 Tengoku Daimakyou 第05话 GB 720P MP4（字幕社招人内详）",
+        sub_group: "极影字幕社",
+        season: 1,
+        episode: 5,
+        name_en: Some("Tengoku Daimakyou"),
+        name_zh: Some("天国大魔境"),
+        name_jp: None,
+        sub: Some("GB"),
+        resolution: Some("720P"),
+        source: None,
+    },
+    Case {
+        title: "[MagicStar] 假面骑士Gotchard / 仮面ライダーガッチャード EP12 [WEBDL] [1080p] [TTFC]【生】",
+        sub_group: "MagicStar",
+        season: 1,
+        episode: 12,
+        name_en: None,
+        name_zh: Some("假面骑士Gotchard"),
+        name_jp: Some("仮面ライダーガッチャード"),
+        sub: None,
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[Nekomoe kissaten][Bocchi the Rock!][03][1080p][JPSC]",
+        sub_group: "Nekomoe kissaten",
+        season: 1,
+        episode: 3,
+        name_en: Some("Bocchi the Rock!"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[Nekomoe kissaten&LoliHouse] Bocchi the Rock! - 12 [WebRip 1080p HEVC-10bit AAC ASSx2]",
+        sub_group: "Nekomoe kissaten&LoliHouse",
+        season: 1,
+        episode: 12,
+        name_en: Some("Bocchi the Rock!"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[Skymoon-Raws] 孤独摇滚！ / Bocchi the Rock! - 08 [ViuTV][WEB-DL][CHT][1080p][AVC AAC]",
+        sub_group: "Skymoon-Raws",
+        season: 1,
+        episode: 8,
+        name_en: Some("Bocchi the Rock!"),
+        name_zh: Some("孤独摇滚！"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[酷漫404][排球少年!! 第四季][11][1080P][WebRip][简日双语][AVC AAC][MP4][字幕组招人内详]",
+        sub_group: "酷漫404",
+        season: 4,
+        episode: 11,
+        name_en: None,
+        name_zh: Some("排球少年!!"),
+        name_jp: None,
+        sub: Some("简日双语"),
+        resolution: Some("1080P"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[ANi] 轉生成為魔劍 - 11 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 11,
+        name_en: None,
+        name_zh: Some("轉生成為魔劍"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[幻樱字幕组][4月新番][擅长捉弄的高木同学 第三季 Karakai Jouzu no Takagi-san S3][06][GB_MP4][1920X1080]",
+        sub_group: "幻樱字幕组",
+        season: 3,
+        episode: 6,
+        name_en: Some("Karakai Jouzu no Takagi-san"),
+        name_zh: Some("擅长捉弄的高木同学"),
+        name_jp: None,
+        sub: Some("GB"),
+        resolution: Some("1920X1080"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 無職轉生，到了異世界就拿出真本事 第二季 - 13 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 2,
+        episode: 13,
+        name_en: None,
+        name_zh: Some("無職轉生，到了異世界就拿出真本事"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[桜都字幕組] 無職轉生 ～到了異世界就拿出真本事～ 第二季 / Mushoku Tensei S2 [05][1080p][繁體內嵌]",
+        sub_group: "桜都字幕組",
+        season: 2,
+        episode: 5,
+        name_en: Some("Mushoku Tensei"),
+        name_zh: Some("無職轉生 ～到了異世界就拿出真本事～"),
+        name_jp: None,
+        sub: Some("繁體內嵌"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[GJ.Y] 葬送的芙莉莲 / Sousou no Frieren - 20 (CR 1920x1080 AVC AAC MKV)",
+        sub_group: "GJ.Y",
+        season: 1,
+        episode: 20,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: Some("葬送的芙莉莲"),
+        name_jp: None,
+        sub: None,
+        resolution: Some("1920x1080"),
+        source: None,
+    },
+    Case {
+        title: "[Erai-raws] Sousou no Frieren - 16 [1080p][Multiple Subtitle][ENG][POR-BR]",
+        sub_group: "Erai-raws",
+        season: 1,
+        episode: 16,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 間諜家家酒 Season 2 - 25 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 2,
+        episode: 25,
+        name_en: None,
+        name_zh: Some("間諜家家酒"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[Lilith-Raws] 因為不是真正的夥伴而被逐出勇者隊伍，流落到邊境展開慢活人生 S02 - 03 [Baha][WEB-DL][1080p][AVC AAC][CHT][MP4]",
+        sub_group: "Lilith-Raws",
+        season: 2,
+        episode: 3,
+        name_en: None,
+        name_zh: Some("因為不是真正的夥伴而被逐出勇者隊伍，流落到邊境展開慢活人生"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080p"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[動漫國字幕組&LoliHouse] THE MARGINAL SERVICE - 08 [WebRip 1080p HEVC-10bit AAC][簡繁內封字幕]",
+        sub_group: "動漫國字幕組&LoliHouse",
+        season: 1,
+        episode: 8,
+        name_en: Some("THE MARGINAL SERVICE"),
+        name_zh: None,
+        name_jp: None,
+        sub: Some("簡繁內封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[星空字幕组] 擅长逃跑的殿下 / Nige Jouzu no Wakagimi [04][CHS][1080p][WEBrip]",
+        sub_group: "星空字幕组",
+        season: 1,
+        episode: 4,
+        name_en: Some("Nige Jouzu no Wakagimi"),
+        name_zh: Some("擅长逃跑的殿下"),
+        name_jp: None,
+        sub: Some("CHS"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[夜莺家族&YYQ字幕组]New Doraemon 哆啦A梦新番[794][2024.02.10][AVC][1080P][GB_JP]",
+        sub_group: "夜莺家族&YYQ字幕组",
+        season: 1,
+        episode: 794,
+        name_en: Some("New Doraemon"),
+        name_zh: Some("哆啦A梦新番"),
+        name_jp: None,
+        sub: Some("GB_JP"),
+        resolution: Some("1080P"),
+        source: None,
+    },
+    Case {
+        title: "[爱恋字幕社][1月新番][金属口红 Metallic Rouge][03][1080P][MP4][GB][简中]",
+        sub_group: "爱恋字幕社",
+        season: 1,
+        episode: 3,
+        name_en: Some("Metallic Rouge"),
+        name_zh: Some("金属口红"),
+        name_jp: None,
+        sub: Some("GB"),
+        resolution: Some("1080P"),
+        source: None,
+    },
+    Case {
+        title: "[悠哈璃羽字幕社] [摇曳露营△ 第三季 Yuru Camp S3] [08] [x264 1080p] [CHS]",
+        sub_group: "悠哈璃羽字幕社",
+        season: 3,
+        episode: 8,
+        name_en: Some("Yuru Camp"),
+        name_zh: Some("摇曳露营△"),
+        name_jp: None,
+        sub: Some("CHS"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[豌豆字幕组&LoliHouse] 王者天下 第五季 / Kingdom S5 - 11 [WebRip 1080p HEVC-10bit AAC][简繁外挂字幕]",
+        sub_group: "豌豆字幕组&LoliHouse",
+        season: 5,
+        episode: 11,
+        name_en: Some("Kingdom"),
+        name_zh: Some("王者天下"),
+        name_jp: None,
+        sub: Some("简繁外挂字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[Billion Meta Lab] 鬼灭之刃 柱训练篇 Kimetsu no Yaiba Hashira Geiko-hen [04][1080P][HEVC 10bit][CHS&CHT]",
+        sub_group: "Billion Meta Lab",
+        season: 1,
+        episode: 4,
+        name_en: Some("Kimetsu no Yaiba Hashira Geiko-hen"),
+        name_zh: Some("鬼灭之刃 柱训练篇"),
+        name_jp: None,
+        sub: Some("CHS&CHT"),
+        resolution: Some("1080P"),
+        source: None,
+    },
+    Case {
+        title: "[千夏字幕组][鬼灭之刃 柱训练篇_Kimetsu no Yaiba Hashira Geiko-hen][第02话][1080p_AVC][简繁内封]",
+        sub_group: "千夏字幕组",
+        season: 1,
+        episode: 2,
+        name_en: Some("Kimetsu no Yaiba Hashira Geiko-hen"),
+        name_zh: Some("鬼灭之刃 柱训练篇"),
+        name_jp: None,
+        sub: Some("简繁内封"),
+        resolution: Some("1080p_AVC"),
+        source: None,
+    },
+    Case {
+        title: "[DMG&LoliHouse] 我心里危险的东西 / Boku no Kokoro no Yabai Yatsu - 25 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]",
+        sub_group: "DMG&LoliHouse",
+        season: 1,
+        episode: 25,
+        name_en: Some("Boku no Kokoro no Yabai Yatsu"),
+        name_zh: Some("我心里危险的东西"),
+        name_jp: None,
+        sub: Some("简繁内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "【悠哈璃羽字幕社】[其实我乃最强？_Jitsu wa Ore, Saikyou deshita][01][x264 1080p][CHS]",
+        sub_group: "悠哈璃羽字幕社",
+        season: 1,
+        episode: 1,
+        name_en: Some("Jitsu wa Ore, Saikyou deshita"),
+        name_zh: Some("其实我乃最强？"),
+        name_jp: None,
+        sub: Some("CHS"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[VCB-Studio] Bocchi the Rock! [01][Ma10p_1080p][x265_flac].mkv",
+        sub_group: "VCB-Studio",
+        season: 1,
+        episode: 1,
+        name_en: Some("Bocchi the Rock!"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("Ma10p_1080p"),
+        source: None,
+    },
+    Case {
+        title: "[VCB-Studio] Kage no Jitsuryokusha ni Naritakute! [12][Ma10p_1080p][x265_flac].mkv",
+        sub_group: "VCB-Studio",
+        season: 1,
+        episode: 12,
+        name_en: Some("Kage no Jitsuryokusha ni Naritakute!"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("Ma10p_1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ReinForce] Sousou no Frieren - 01 (BDRip 1920x1080 x264 FLAC).mkv",
+        sub_group: "ReinForce",
+        season: 1,
+        episode: 1,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("1920x1080"),
+        source: None,
+    },
+    Case {
+        title: "[MTBB] Sousou no Frieren - 02 [5D2C0E4F].mkv",
+        sub_group: "MTBB",
+        season: 1,
+        episode: 2,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: None,
+        source: None,
+    },
+    Case {
+        title: "[Moozzi2] Sousou no Frieren - 04 (BD 1920x1080 x265-10Bit Flac).mkv",
+        sub_group: "Moozzi2",
+        season: 1,
+        episode: 4,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("1920x1080"),
+        source: None,
+    },
+    Case {
+        title: "[jsum] Bocchi the Rock! - 05 [BDRip 1080p][E21F2C89].mkv",
+        sub_group: "jsum",
+        season: 1,
+        episode: 5,
+        name_en: Some("Bocchi the Rock!"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 藥師少女的獨語 - 24 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 24,
+        name_en: None,
+        name_zh: Some("藥師少女的獨語"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[Lilith-Raws] 藥師少女的獨語 / Kusuriya no Hitorigoto - 20v2 [Baha][WebDL 1080p AVC AAC][CHT]",
+        sub_group: "Lilith-Raws",
+        season: 1,
+        episode: 20,
+        name_en: Some("Kusuriya no Hitorigoto"),
+        name_zh: Some("藥師少女的獨語"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080p"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[ANi] 青之驅魔師 島根啟明結社篇 - 03 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 3,
+        name_en: None,
+        name_zh: Some("青之驅魔師 島根啟明結社篇"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[北宇治字幕组] 蓝色监狱 / Blue Lock [15v2][WebRip][1080p][HEVC_AAC][简日内嵌]",
+        sub_group: "北宇治字幕组",
+        season: 1,
+        episode: 15,
+        name_en: Some("Blue Lock"),
+        name_zh: Some("蓝色监狱"),
+        name_jp: None,
+        sub: Some("简日内嵌"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[喵萌Production&LoliHouse] 少女歌剧 / Shoujo Kageki Revue Starlight - 12 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕]",
+        sub_group: "喵萌Production&LoliHouse",
+        season: 1,
+        episode: 12,
+        name_en: Some("Shoujo Kageki Revue Starlight"),
+        name_zh: Some("少女歌剧"),
+        name_jp: None,
+        sub: Some("简繁日内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[SBSUB][CONAN][1115][WEBRIP][1080P][HEVC_AAC][CHS_CHT_JP](E1A8F0B3)",
+        sub_group: "SBSUB",
+        season: 1,
+        episode: 1115,
+        name_en: Some("CONAN"),
+        name_zh: None,
+        name_jp: None,
+        sub: Some("CHS_CHT_JP"),
+        resolution: Some("1080P"),
+        source: None,
+    },
+    Case {
+        title: "[桜都字幕组][海贼王/One Piece][1088][1080P][简体内嵌]",
+        sub_group: "桜都字幕组",
+        season: 1,
+        episode: 1088,
+        name_en: Some("One Piece"),
+        name_zh: Some("海贼王"),
+        name_jp: None,
+        sub: Some("简体内嵌"),
+        resolution: Some("1080P"),
+        source: None,
+    },
+    Case {
+        title: "[Lilith-Raws] 海賊王 / One Piece - 1095 [Baha][WEB-DL][1080p][AVC AAC][CHT][MP4]",
+        sub_group: "Lilith-Raws",
+        season: 1,
+        episode: 1095,
+        name_en: Some("One Piece"),
+        name_zh: Some("海賊王"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080p"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[猎户不鸽压制] 海贼王 One Piece [1100] [1080p] [简中内嵌] [2024年4月番]",
+        sub_group: "猎户不鸽压制",
+        season: 1,
+        episode: 1100,
+        name_en: Some("One Piece"),
+        name_zh: Some("海贼王"),
+        name_jp: None,
+        sub: Some("简中内嵌"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 物語系列 第外季&第怪季 - 07 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 7,
+        name_en: None,
+        name_zh: Some("物語系列 第外季&第怪季"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[LoliHouse] 魔法少女小圆 剧场版 - 01 [BDRip 1080p]",
+        sub_group: "LoliHouse",
+        season: 1,
+        episode: 1,
+        name_en: None,
+        name_zh: Some("魔法少女小圆 剧场版"),
+        name_jp: None,
+        sub: None,
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 迷宮飯 - 24 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 24,
+        name_en: None,
+        name_zh: Some("迷宮飯"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[LoliHouse] 败犬女主太多了！ / Make Heroine ga Oosugiru! - 12 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕][END]",
+        sub_group: "LoliHouse",
+        season: 1,
+        episode: 12,
+        name_en: Some("Make Heroine ga Oosugiru!"),
+        name_zh: Some("败犬女主太多了！"),
+        name_jp: None,
+        sub: Some("简繁内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[桜都字幕组] 败犬女主太多了！ / Make Heroine ga Oosugiru! [06][1080p][简体内嵌]",
+        sub_group: "桜都字幕组",
+        season: 1,
+        episode: 6,
+        name_en: Some("Make Heroine ga Oosugiru!"),
+        name_zh: Some("败犬女主太多了！"),
+        name_jp: None,
+        sub: Some("简体内嵌"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[Lilith-Raws] 敗北女角太多了！ / Make Heroine ga Oosugiru! - 08 [Baha][WEB-DL][1080p][AVC AAC][CHT][MP4]",
+        sub_group: "Lilith-Raws",
+        season: 1,
+        episode: 8,
+        name_en: Some("Make Heroine ga Oosugiru!"),
+        name_zh: Some("敗北女角太多了！"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080p"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[ANi] 膽大黨 - 04 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 4,
+        name_en: None,
+        name_zh: Some("膽大黨"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[喵萌奶茶屋&LoliHouse] 超自然武装当哒当 / Dandadan - 09 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕]",
+        sub_group: "喵萌奶茶屋&LoliHouse",
+        season: 1,
+        episode: 9,
+        name_en: Some("Dandadan"),
+        name_zh: Some("超自然武装当哒当"),
+        name_jp: None,
+        sub: Some("简繁日内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[SweetSub] 超自然武装当哒当 / Dandadan [10][WebRip][1080P][AVC 8bit][简体]",
+        sub_group: "SweetSub",
+        season: 1,
+        episode: 10,
+        name_en: Some("Dandadan"),
+        name_zh: Some("超自然武装当哒当"),
+        name_jp: None,
+        sub: Some("简体"),
+        resolution: Some("1080P"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[ANi] 香格里拉・開拓異境～糞作獵手挑戰神作～ 第二季 - 05 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 2,
+        episode: 5,
+        name_en: None,
+        name_zh: Some("香格里拉・開拓異境～糞作獵手挑戰神作～"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[LoliHouse] 香格里拉·弗陇提亚 第二季 / Shangri-La Frontier S2 - 05 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]",
+        sub_group: "LoliHouse",
+        season: 2,
+        episode: 5,
+        name_en: Some("Shangri-La Frontier"),
+        name_zh: Some("香格里拉·弗陇提亚"),
+        name_jp: None,
+        sub: Some("简繁内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[桜都字幕组] 赛马娘 芦毛灰姑娘 / Uma Musume Cinderella Gray [03][1080p][简繁内封]",
+        sub_group: "桜都字幕组",
+        season: 1,
+        episode: 3,
+        name_en: Some("Uma Musume Cinderella Gray"),
+        name_zh: Some("赛马娘 芦毛灰姑娘"),
+        name_jp: None,
+        sub: Some("简繁内封"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 地。-關於地球的運動- - 25 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 25,
+        name_en: None,
+        name_zh: Some("地。-關於地球的運動-"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[LoliHouse] 地。-关于地球的运动- / Chi. Chikyuu no Undou ni Tsuite - 07 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]",
+        sub_group: "LoliHouse",
+        season: 1,
+        episode: 7,
+        name_en: Some("Chi. Chikyuu no Undou ni Tsuite"),
+        name_zh: Some("地。-关于地球的运动-"),
+        name_jp: None,
+        sub: Some("简繁内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[猎户手抄部] 葬送的芙莉莲 / Sousou no Frieren [09] [1080p] [简日内嵌]",
+        sub_group: "猎户手抄部",
+        season: 1,
+        episode: 9,
+        name_en: Some("Sousou no Frieren"),
+        name_zh: Some("葬送的芙莉莲"),
+        name_jp: None,
+        sub: Some("简日内嵌"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 成為冒險家吧！～用技能卡片在地下城內探索～ - 06 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 6,
+        name_en: None,
+        name_zh: Some("成為冒險家吧！～用技能卡片在地下城內探索～"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[GJ.Y] 魔都精兵的奴隶 / Mato Seihei no Slave - 04 (B-Global 3840x2160 HEVC AAC MKV)",
+        sub_group: "GJ.Y",
+        season: 1,
+        episode: 4,
+        name_en: Some("Mato Seihei no Slave"),
+        name_zh: Some("魔都精兵的奴隶"),
+        name_jp: None,
+        sub: None,
+        resolution: Some("3840x2160"),
+        source: None,
+    },
+    Case {
+        title: "[Lilith-Raws] 魔都精兵的奴隸 / Mato Seihei no Slave - 10 [Baha][WEB-DL][1080p][AVC AAC][CHT][MP4]",
+        sub_group: "Lilith-Raws",
+        season: 1,
+        episode: 10,
+        name_en: Some("Mato Seihei no Slave"),
+        name_zh: Some("魔都精兵的奴隸"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080p"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[爱恋字幕社][4月新番][怪兽8号][Kaijuu 8-gou][05][1080p][MP4][简中]",
+        sub_group: "爱恋字幕社",
+        season: 1,
+        episode: 5,
+        name_en: Some("Kaijuu 8-gou"),
+        name_zh: Some("怪兽8号"),
+        name_jp: None,
+        sub: Some("简中"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 怪獸8號 - 11 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 11,
+        name_en: None,
+        name_zh: Some("怪獸8號"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[ANi] 不時輕聲地以俄語遮羞的鄰座艾莉同學 - 03 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 3,
+        name_en: None,
+        name_zh: Some("不時輕聲地以俄語遮羞的鄰座艾莉同學"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[LoliHouse] 不时轻声地以俄语遮羞的邻座艾莉同学 / Tokidoki Bosotto Russia-go de Dereru Tonari no Alya-san - 12 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕][END]",
+        sub_group: "LoliHouse",
+        season: 1,
+        episode: 12,
+        name_en: Some("Tokidoki Bosotto Russia-go de Dereru Tonari no Alya-san"),
+        name_zh: Some("不时轻声地以俄语遮羞的邻座艾莉同学"),
+        name_jp: None,
+        sub: Some("简繁内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[豌豆字幕组&LoliHouse] 药屋少女的呢喃 / Kusuriya no Hitorigoto - 30 [WebRip 1080p HEVC-10bit AAC][简繁外挂字幕]",
+        sub_group: "豌豆字幕组&LoliHouse",
+        season: 1,
+        episode: 30,
+        name_en: Some("Kusuriya no Hitorigoto"),
+        name_zh: Some("药屋少女的呢喃"),
+        name_jp: None,
+        sub: Some("简繁外挂字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[喵萌奶茶屋&LoliHouse] 魔女之旅 / Majo no Tabitabi - 03 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕]",
+        sub_group: "喵萌奶茶屋&LoliHouse",
+        season: 1,
+        episode: 3,
+        name_en: Some("Majo no Tabitabi"),
+        name_zh: Some("魔女之旅"),
+        name_jp: None,
+        sub: Some("简繁日内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[Skymoon-Raws] 我的英雄學院 第七季 / Boku no Hero Academia S7 - 05 [ViuTV][WEB-DL][CHT][1080p][AVC AAC]",
+        sub_group: "Skymoon-Raws",
+        season: 7,
+        episode: 5,
+        name_en: Some("Boku no Hero Academia"),
+        name_zh: Some("我的英雄學院"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 我的英雄學院 第七季 - 12 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 7,
+        episode: 12,
+        name_en: None,
+        name_zh: Some("我的英雄學院"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[Nekomoe kissaten&LoliHouse] Girls Band Cry - 13 [WebRip 1080p HEVC-10bit AAC ASSx2]",
+        sub_group: "Nekomoe kissaten&LoliHouse",
+        season: 1,
+        episode: 13,
+        name_en: Some("Girls Band Cry"),
+        name_zh: None,
+        name_jp: None,
+        sub: None,
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[拨雪寻春] 少女乐队的呐喊 / Girls Band Cry [07][1080p][简繁日内封]",
+        sub_group: "拨雪寻春",
+        season: 1,
+        episode: 7,
+        name_en: Some("Girls Band Cry"),
+        name_zh: Some("少女乐队的呐喊"),
+        name_jp: None,
+        sub: Some("简繁日内封"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 小市民系列 - 10 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 10,
+        name_en: None,
+        name_zh: Some("小市民系列"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[ANi] 蔚藍檔案 - 12 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 1,
+        episode: 12,
+        name_en: None,
+        name_zh: Some("蔚藍檔案"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[MingYSub] 咒术回战 第二季 / Jujutsu Kaisen S2 - 40 [1080p][简日内嵌]",
+        sub_group: "MingYSub",
+        season: 2,
+        episode: 40,
+        name_en: Some("Jujutsu Kaisen"),
+        name_zh: Some("咒术回战"),
+        name_jp: None,
+        sub: Some("简日内嵌"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[LoliHouse] 咒术回战 / Jujutsu Kaisen - 47 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]",
+        sub_group: "LoliHouse",
+        season: 1,
+        episode: 47,
+        name_en: Some("Jujutsu Kaisen"),
+        name_zh: Some("咒术回战"),
+        name_jp: None,
+        sub: Some("简繁内封字幕"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[动漫国字幕组][10月新番][葬送的芙莉莲][14][1080P][MP4][简体]",
+        sub_group: "动漫国字幕组",
+        season: 1,
+        episode: 14,
+        name_en: None,
+        name_zh: Some("葬送的芙莉莲"),
+        name_jp: None,
+        sub: Some("简体"),
+        resolution: Some("1080P"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 我推的孩子 第二季 - 13 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 2,
+        episode: 13,
+        name_en: None,
+        name_zh: Some("我推的孩子"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[北宇治字幕组] 我推的孩子 第二季 / Oshi no Ko S2 [18][WebRip][1080p][HEVC_AAC][简日内嵌]",
+        sub_group: "北宇治字幕组",
+        season: 2,
+        episode: 18,
+        name_en: Some("Oshi no Ko"),
+        name_zh: Some("我推的孩子"),
+        name_jp: None,
+        sub: Some("简日内嵌"),
+        resolution: Some("1080p"),
+        source: Some("WebRip"),
+    },
+    Case {
+        title: "[7³ACG] 摇曳露营△ 第三季/Yuru Camp S3 [09] [1080p] [简繁内封]",
+        sub_group: "7³ACG",
+        season: 3,
+        episode: 9,
+        name_en: Some("Yuru Camp"),
+        name_zh: Some("摇曳露营△"),
+        name_jp: None,
+        sub: Some("简繁内封"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+    Case {
+        title: "[ANi] 戀上換裝娃娃 第二季 - 02 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+        sub_group: "ANi",
+        season: 2,
+        episode: 2,
+        name_en: None,
+        name_zh: Some("戀上換裝娃娃"),
+        name_jp: None,
+        sub: Some("CHT"),
+        resolution: Some("1080P"),
+        source: Some("Baha"),
+    },
+    Case {
+        title: "[桜都字幕组] 更衣人偶坠入爱河 第二季 / Sono Bisque Doll wa Koi wo Suru S2 [03][1080p][简繁内封]",
+        sub_group: "桜都字幕组",
+        season: 2,
+        episode: 3,
+        name_en: Some("Sono Bisque Doll wa Koi wo Suru"),
+        name_zh: Some("更衣人偶坠入爱河"),
+        name_jp: None,
+        sub: Some("简繁内封"),
+        resolution: Some("1080p"),
+        source: None,
+    },
+];
+
+// (title, season, start, end, fin)
+const BATCH_CASES: &[(&str, u8, u32, u32, bool)] = &[
+    ("[DBD-Raws][孤独摇滚！/Bocchi the Rock!][01-12TV全集][1080P][BDRip][HEVC-10bit][FLAC][MKV]", 1, 1, 12, false),
+    ("[桜都字幕组] 药屋少女的呢喃 / Kusuriya no Hitorigoto [01-24 合集][1080p][简繁内封]", 1, 1, 24, false),
+    ("[Nekomoe kissaten&LoliHouse] Bocchi the Rock! [01-12 Fin][WebRip 1080p HEVC-10bit AAC ASSx2]", 1, 1, 12, true),
+    ("[织梦字幕组][咒术回战 第二季 Jujutsu Kaisen S2][第01-23话][1080P][AVC][简日双语]", 2, 1, 23, false),
+    ("[Erai-raws] Sousou no Frieren S01E01-E28 [1080p][Multiple Subtitle]", 1, 1, 28, false),
+    ("[ANi] 葬送的芙莉蓮 - 01~28 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]", 1, 1, 28, false),
+    ("[LoliHouse] 迷宫饭 / Dungeon Meshi [01-24 END][WebRip 1080p HEVC-10bit AAC][简繁内封字幕]", 1, 1, 24, true),
+    ("[Erai-raws] Spy x Family Season 2 S02E13-E25 END [1080p]", 2, 13, 25, true),
+];
+
+// (title, season, episode, name), name 为 Episode::name(None) 的结果
+const SEASON_CASES: &[(&str, u8, u32, &str)] = &[
+    ("[ANi] 哥布林殺手 II - 12 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]", 2, 12, "哥布林殺手"),
+    ("[ANi] Overlord IV - 13 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]", 4, 13, "Overlord"),
+    ("[Up to 21°C] 我推的孩子 第二季 / Oshi no Ko 2nd Season - 20 (B-Global 1920x1080 HEVC AAC MKV)", 2, 20, "我推的孩子"),
+    ("[Sakurato] Kage no Jitsuryokusha ni Naritakute! 2nd Season [08][HEVC-10bit 1080p AAC][CHS&CHT].mkv", 2, 8, "Kage no Jitsuryokusha ni Naritakute!"),
+    ("[Up to 21°C] 关于我转生变成史莱姆这档事 第三季 / Tensei shitara Slime Datta Ken 3rd Season - 49 (Baha 1920x1080 AVC AAC MP4)", 3, 49, "关于我转生变成史莱姆这档事"),
+    ("[黒ネズミたち] 摇曳露营△ 第三季 / Yuru Camp Season 3 - 10 (B-Global 1920x1080 HEVC AAC MKV)", 3, 10, "摇曳露营△"),
+    ("[漫猫字幕社][4月新番][无职转生 第二季][Mushoku Tensei S2][17][1080P][MP4][简日双语]", 2, 17, "无职转生"),
+    ("[Nekomoe kissaten] Kaguya-sama wa Kokurasetai Second Season [04][1080p][CHS]", 2, 4, "Kaguya-sama wa Kokurasetai"),
+    ("[Erai-raws] Shingeki no Kyojin - The Final Season Part 2 - 05 [1080p][Multiple Subtitle]", 2, 5, "Shingeki no Kyojin"),
+    ("[LoliHouse] 进击的巨人 最终季 Part.2 / Shingeki no Kyojin The Final Season Part 2 - 80 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]", 2, 80, "进击的巨人 最终季"),
+    ("[ANi] 86－不存在的戰區－ 第2クール - 13 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]", 2, 13, "86－不存在的戰區－"),
+    ("[LoliHouse] ようこそ実力至上主義の教室へ 第3期 / Youkoso Jitsuryoku Shijou Shugi no Kyoushitsu e 3rd Season - 05 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]", 3, 5, "ようこそ実力至上主義の教室へ"),
+    ("[桜都字幕组] 名侦探柯南 第十一季 [05][1080p][简体内嵌]", 11, 5, "名侦探柯南"),
+    ("[喵萌奶茶屋&LoliHouse] 某番剧 第二十季 / Some Show Season 20 - 01 [WebRip 1080p][简繁日内封字幕]", 20, 1, "某番剧"),
+    ("[SweetSub] 某科学的超电磁炮T / Toaru Kagaku no Railgun T [25][WebRip][1080P][AVC 8bit][简体]", 1, 25, "某科学的超电磁炮T"),
+];
+
+const SP_CASES: &[&str] = &[
+    "[Up to 21°C] 擅长逃跑的殿下 / Nige Jouzu no Wakagimi - 9.5 (Baha 1920x1080 AVC AAC MP4)",
+    "[VCB-Studio] Kage no Jitsuryokusha ni Naritakute! [SP01][Ma10p_1080p][x265_flac].mkv",
+    "[Nekomoe kissaten&VCB-Studio] Bocchi the Rock! [Menu01][Ma10p_1080p][x265_flac].mkv",
+];
+
+const ERR_CASES: &[&str] = &["", "【】[]"];
+
+#[test]
+fn test_ep_corpus() {
+    for case in EP_CASES {
+        let ep = match process(case.title) {
+            Ok(Episode::Ep(ep)) => ep,
+            x => panic!("{}: expect ep, got {:?}", case.title, x),
+        };
+        assert_eq!(ep.sub_group, case.sub_group, "{}", case.title);
+        assert_eq!(ep.season, case.season, "{}", case.title);
+        assert_eq!(ep.episode, case.episode, "{}", case.title);
+        assert_eq!(ep.name_en.as_deref(), case.name_en, "{}", case.title);
+        assert_eq!(ep.name_zh.as_deref(), case.name_zh, "{}", case.title);
+        assert_eq!(ep.name_jp.as_deref(), case.name_jp, "{}", case.title);
+        assert_eq!(ep.sub.as_deref(), case.sub, "{}", case.title);
+        assert_eq!(ep.resolution.as_deref(), case.resolution, "{}", case.title);
+        assert_eq!(ep.source.as_deref(), case.source, "{}", case.title);
+        assert!(ep.confidence >= 60, "{}: {}", case.title, ep.confidence);
+    }
+}
+
+#[test]
+fn test_batch_corpus() {
+    for (title, season, start, end, fin) in BATCH_CASES {
+        let batch = match process(title) {
+            Ok(Episode::Batch(batch)) => batch,
+            x => panic!("{title}: expect batch, got {x:?}"),
+        };
+        assert_eq!(batch.ep.season, *season, "{title}");
+        assert_eq!(batch.episodes(), *start..=*end, "{title}");
+        assert_eq!(batch.fin, *fin, "{title}");
+    }
+}
+
+#[test]
+fn test_season_corpus() {
+    for (title, season, episode, name) in SEASON_CASES {
+        let ep = match process(title) {
+            Ok(Episode::Ep(ep)) => ep,
+            x => panic!("{title}: expect ep, got {x:?}"),
+        };
+        assert_eq!(ep.season, *season, "{title}");
+        assert_eq!(ep.episode, *episode, "{title}");
+        assert_eq!(Episode::Ep(ep).name(None).unwrap(), *name, "{title}");
+    }
+}
+
+#[test]
+fn test_sp_corpus() {
+    for title in SP_CASES {
+        let ep = process(title);
+        assert!(
+            matches!(&ep, Ok(Episode::Sp { name }) if name == title),
+            "{title}: expect sp, got {ep:?}"
+        );
+        assert_eq!(ep.unwrap().confidence(), 0);
+    }
+}
+
+#[test]
+fn test_err_corpus() {
+    for title in ERR_CASES {
+        assert!(process(title).is_err(), "{title}");
+    }
+}
+
+#[test]
+fn test_confidence() {
+    // 独立ep块 + 括号group + 全部tag
+    let full = process(
+        "[北宇治字幕组] 我推的孩子 第二季 / Oshi no Ko S2 [18][WebRip][1080p][HEVC_AAC][简日内嵌]",
+    )
+    .unwrap();
+    assert_eq!(full.confidence(), 100);
+    // name中的ep, 没有tag
+    let bare = process("[MTBB] Sousou no Frieren - 02 [5D2C0E4F].mkv").unwrap();
+    assert!(bare.confidence() < full.confidence());
+}
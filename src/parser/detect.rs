@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use color_eyre::eyre::{bail, Result};
+use regex::{Captures, Regex};
+
+use super::{Context, Detector, SubType};
+
+static RESOLUTION_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"1080|720|2160|4K").unwrap());
+static SOURCE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"B-Global|[Bb]aha|[Bb]ilibili|AT-X|Web").unwrap());
+static SUB_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[简繁日字幕]|CH|BIG5|GB").unwrap());
+static CLEAN_SUB_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"_MP4|_MKV").unwrap());
+// 编码等标签常和其他标签用 - _ 连在一起, 如 HEVC-10bit HEVC_AAC x265_flac, 所以不能用 \b
+static VIDEO_CODEC_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:^|[^a-z0-9])(AVC|HEVC|AV1|[xh]\.?26[45])(?:[^a-z0-9]|$)").unwrap()
+});
+static BIT_DEPTH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:^|[^a-z0-9])(?:(8|10|12) ?-?bits?|(?:Ma|Hi)(10)p)(?:[^a-z0-9]|$)").unwrap()
+});
+static AUDIO_CODEC_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:^|[^a-z0-9])(AAC|FLAC|Opus|E?AC3|DTS|TrueHD)(?:x\d)?(?:[^a-z0-9]|$)")
+        .unwrap()
+});
+static CONTAINER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:^|[^a-z0-9])(MP4|MKV)(?:[^a-z0-9]|$)").unwrap());
+static CRC32_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[0-9A-Fa-f]{8}$").unwrap());
+static SUB_TYPE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"内嵌|內嵌|内封|內封|外挂|外掛").unwrap());
+
+/*
+   S02 | Season 2 | 2nd Season | Second Season | Part 2 Part.2 | 第二季 第2期 第十一部 第2クール | 罗马数字 II-IX
+   罗马数字需要前面有空格, I V X 容易误判所以不支持
+*/
+const SEASON_PATTERN: &str = r"S(\d{1,2})|Season (\d{1,2})|(\d{1,2})(?:st|nd|rd|th) [Ss]eason|(?i:(first|second|third|fourth|fifth|sixth|seventh|eighth|ninth|tenth) season)|[Pp]art[ .]?(\d{1,2})|[第 ]([一二三四五六七八九十]{1,2}|\d{1,2})(?:部分|[季期部]|クール)| (II|III|IV|VI|VII|VIII|IX)\b";
+static SEASON_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(SEASON_PATTERN).unwrap());
+// 标题被空格切分成块时, 用于匹配相邻的两块, 如 `Season` `3`
+static SEASON_PAIR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!("^(?:{SEASON_PATTERN})$")).unwrap());
+static EP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:(\d+)|(\d+).?[vV](?<version>\d)|第?(\d+)[话話集]|(\d+).?END)$").unwrap()
+});
+static EP_FROM_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r" -? ?(?:(\d+)|第(\d+)[话話集]|[Ee][Pp]?(\d+))(?:.?[vV](?<version>\d))?").unwrap()
+});
+static BATCH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:[Ee][Pp]?|第)?(\d{1,4}) ?[-~～] ?(?:[Ee][Pp]?)?(\d{1,4})[话話集]? ?(?:TV|BD|Web)?(?:全集|合集)? ?(?<fin>[Ff][Ii][Nn]|E[Nn][Dd]|完结?)?$").unwrap()
+});
+static BATCH_FROM_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?: (?:- )?第?(\d{1,4})[-~～](\d{1,4})[话話集]?| S(?<season>\d{1,2})E(\d{1,4})-E?(\d{1,4}))(?: (?<fin>[Ff][Ii][Nn]|E[Nn][Dd]|完结?))?$").unwrap()
+});
+
+static JP_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\u0800-\u4e00]{2,}").unwrap());
+static ZH_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\u4e00-\u9fa5]{2,}").unwrap());
+static EN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[a-zA-Z]{2,}").unwrap());
+static REGION_LIMIT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[(（][仅僅]限港澳台地[区區][）)]").unwrap());
+static NAME_SPLIT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/|\s{2}|-\s{2}").unwrap());
+static UNDERSCORE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"_").unwrap());
+static SPACED_DASH_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r" - ").unwrap());
+
+static CHINESE_NUMBER_MAP: LazyLock<HashMap<&'static str, u8>> = LazyLock::new(|| {
+    let mut map = HashMap::new();
+    map.insert("一", 1);
+    map.insert("二", 2);
+    map.insert("三", 3);
+    map.insert("四", 4);
+    map.insert("五", 5);
+    map.insert("六", 6);
+    map.insert("七", 7);
+    map.insert("八", 8);
+    map.insert("九", 9);
+    map.insert("十", 10);
+    map.insert("十一", 11);
+    map.insert("十二", 12);
+    map.insert("十三", 13);
+    map.insert("十四", 14);
+    map.insert("十五", 15);
+    map.insert("十六", 16);
+    map.insert("十七", 17);
+    map.insert("十八", 18);
+    map.insert("十九", 19);
+    map.insert("二十", 20);
+    map
+});
+
+static ROMAN_NUMBER_MAP: LazyLock<HashMap<&'static str, u8>> = LazyLock::new(|| {
+    let mut map = HashMap::new();
+    map.insert("II", 2);
+    map.insert("III", 3);
+    map.insert("IV", 4);
+    map.insert("VI", 6);
+    map.insert("VII", 7);
+    map.insert("VIII", 8);
+    map.insert("IX", 9);
+    map
+});
+
+static ORDINAL_NUMBER_MAP: LazyLock<HashMap<&'static str, u8>> = LazyLock::new(|| {
+    let mut map = HashMap::new();
+    map.insert("first", 1);
+    map.insert("second", 2);
+    map.insert("third", 3);
+    map.insert("fourth", 4);
+    map.insert("fifth", 5);
+    map.insert("sixth", 6);
+    map.insert("seventh", 7);
+    map.insert("eighth", 8);
+    map.insert("ninth", 9);
+    map.insert("tenth", 10);
+    map
+});
+
+/// 从正则的捕获组中取出第一个命中的组
+fn first_capture<'h>(re: &Regex, haystack: &'h str) -> Option<&'h str> {
+    re.captures(haystack)
+        .and_then(|c| c.iter().skip(1).find_map(|m| m.map(|m| m.as_str())))
+}
+
+/// 块1: group, 以括号开头的标题可信度更高
+pub struct GroupDetector;
+
+impl Detector for GroupDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        ctx.group = ctx.blocks.first().cloned();
+        if ctx.title.trim_start().starts_with(['[', '【']) {
+            ctx.add_confidence(10);
+        }
+        Ok(())
+    }
+}
+
+/// 在group以外的块中寻找第一个season, 并把它从块中去除
+pub struct SeasonDetector;
+
+fn parse_season(m: &str) -> Option<u8> {
+    match m.parse::<u8>() {
+        Ok(season_int) => Some(season_int),
+        Err(_) => CHINESE_NUMBER_MAP
+            .get(m)
+            .or_else(|| ROMAN_NUMBER_MAP.get(m))
+            .or_else(|| ORDINAL_NUMBER_MAP.get(m.to_lowercase().as_str()))
+            .copied(),
+    }
+}
+
+/// 在任意文本(如文件夹名)中寻找season, 支持的写法和 [`SeasonDetector`] 相同
+pub fn find_season(text: &str) -> Option<u8> {
+    first_capture(&SEASON_RE, text).and_then(parse_season)
+}
+
+impl Detector for SeasonDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        let mut maybe_season = None;
+        for index in 1..ctx.blocks.len() {
+            // 去除所有Season信息, 只使用第一个
+            let block = &ctx.blocks[index];
+            if let Some(season) = first_capture(&SEASON_RE, block).and_then(parse_season) {
+                maybe_season = maybe_season.or(Some(season));
+                ctx.blocks[index] = SEASON_RE.replace_all(block, "").to_string();
+                continue;
+            }
+            let Some(next) = ctx.blocks.get(index + 1) else {
+                continue;
+            };
+            let pair = format!("{block} {next}");
+            if let Some(season) = first_capture(&SEASON_PAIR_RE, &pair).and_then(parse_season) {
+                maybe_season = maybe_season.or(Some(season));
+                ctx.blocks[index].clear();
+                ctx.blocks[index + 1].clear();
+            }
+        }
+        if ctx.season.is_none() && maybe_season.is_some() {
+            ctx.season = maybe_season;
+            ctx.add_confidence(5);
+        }
+        Ok(())
+    }
+}
+
+/// 订阅中配置的 season_regex, 在整个标题上匹配, 取第一个捕获组
+pub struct RegexSeasonDetector(pub Regex);
+
+impl Detector for RegexSeasonDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        if let Some(season) = first_capture(&self.0, &ctx.title).and_then(parse_season) {
+            ctx.season = Some(season);
+            ctx.add_confidence(5);
+        }
+        Ok(())
+    }
+}
+
+/**
+ * 订阅中配置的 episode_regex, 取第一个捕获组
+ * 先逐块匹配: 匹配整个块时当作独立ep块, 否则当作name中的ep
+ * 逐块匹配不到时再匹配整个标题, 此时不再区分name块
+ */
+pub struct RegexEpisodeDetector(pub Regex);
+
+impl Detector for RegexEpisodeDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        for (index, block) in ctx.blocks[1..].iter_mut().enumerate() {
+            let Some(c) = self.0.captures(block) else {
+                continue;
+            };
+            let Some(ep) = c
+                .iter()
+                .skip(1)
+                .find_map(|m| m.and_then(|m| m.as_str().parse::<u32>().ok()))
+            else {
+                continue;
+            };
+            let m = c.get(0).unwrap(); // group 0 always exists
+            if m.as_str() == block.as_str() {
+                ctx.name_block_end = Some(index);
+            } else {
+                *block = block[..m.start()].to_string();
+                ctx.name_block_end = Some(index + 1);
+            }
+            ctx.episode = Some(ep);
+            ctx.add_confidence(50);
+            return Ok(());
+        }
+
+        if let Some(ep) = first_capture(&self.0, &ctx.title).and_then(|s| s.parse::<u32>().ok()) {
+            ctx.episode = Some(ep);
+            ctx.name_block_end = Some(0);
+            ctx.add_confidence(50);
+        }
+        Ok(())
+    }
+}
+
+/**
+ * 合集, 如 [01-12] [01-12 Fin] [第01-13话] [01-24 合集] `name S01E01-E12`
+ * 记录起止集数和是否完结, 起始集数写入episode
+ */
+pub struct BatchDetector;
+
+/// 取出前两个命中的匿名捕获组作为起止集数, 起始集数必须小于结束集数
+fn batch_range(re: &Regex, c: &Captures) -> Option<(u32, u32)> {
+    let mut numbers = re
+        .capture_names()
+        .zip(c.iter())
+        .skip(1)
+        .filter(|(name, _)| name.is_none())
+        .filter_map(|(_, m)| m?.as_str().parse::<u32>().ok());
+    let (start, end) = (numbers.next()?, numbers.next()?);
+    (start < end).then_some((start, end))
+}
+
+impl Detector for BatchDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        if ctx.episode.is_some() {
+            return Ok(());
+        }
+        for (index, block) in ctx.blocks[1..].iter_mut().enumerate() {
+            if let Some((c, (start, end))) = BATCH_RE
+                .captures(block)
+                .and_then(|c| batch_range(&BATCH_RE, &c).map(|r| (c, r)))
+            {
+                ctx.episode = Some(start);
+                ctx.episode_end = Some(end);
+                ctx.fin = c.name("fin").is_some();
+                ctx.name_block_end = Some(index);
+                ctx.add_confidence(50);
+                return Ok(());
+            }
+            if let Some((c, (start, end))) = BATCH_FROM_NAME_RE
+                .captures(block)
+                .and_then(|c| batch_range(&BATCH_FROM_NAME_RE, &c).map(|r| (c, r)))
+            {
+                if let Some(season) = c.name("season").and_then(|m| m.as_str().parse().ok()) {
+                    ctx.season = Some(season);
+                }
+                ctx.episode = Some(start);
+                ctx.episode_end = Some(end);
+                ctx.fin = c.name("fin").is_some();
+                let m_start = c.get(0).unwrap().start(); // group 0 always exists
+                *block = block[..m_start].to_string();
+                ctx.name_block_end = Some(index + 1);
+                ctx.add_confidence(35);
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 独立的ep块, 如 [11] [02集] [26 END], 它的前一个块是name块
+pub struct StandaloneEpisodeDetector;
+
+impl Detector for StandaloneEpisodeDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        if ctx.episode.is_some() {
+            return Ok(());
+        }
+        for (index, block) in ctx.blocks[1..].iter().enumerate() {
+            if let Some(ep) = first_capture(&EP_RE, block).and_then(|s| s.parse::<u32>().ok()) {
+                ctx.episode = Some(ep);
+                ctx.name_block_end = Some(index);
+                ctx.add_confidence(50);
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 包含在name块中的ep, 如 `name - 01` `name 第05话` `name EP33`, ep之后不能再有其他内容
+pub struct EpisodeInNameDetector;
+
+impl Detector for EpisodeInNameDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        if ctx.episode.is_some() {
+            return Ok(());
+        }
+        let mut tag_block = None;
+        for (index, block) in ctx.blocks[1..].iter_mut().enumerate() {
+            let Some(ep) =
+                first_capture(&EP_FROM_NAME_RE, block).and_then(|s| s.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            // 只保留第一个匹配之前的字符
+            let m = EP_FROM_NAME_RE.find(block).unwrap(); // captures matched
+                                                          // 如 `Name - 01 (1080p)`, 括号里的内容当作单独的tag块
+            let rest = block[m.end()..].trim();
+            if !rest.is_empty() {
+                match rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+                    Some(tags) => tag_block = Some((index + 2, tags.to_string())),
+                    None => bail!("can't understand episode number for title: {}", ctx.title),
+                }
+            }
+            *block = block[..m.start()].to_string();
+            ctx.episode = Some(ep);
+            ctx.name_block_end = Some(index + 1);
+            ctx.add_confidence(35);
+            break;
+        }
+        if let Some((index, tags)) = tag_block {
+            ctx.blocks.insert(index, tags);
+        }
+        Ok(())
+    }
+}
+
+/// 把group之后到ep之前的块当作name, 拆分出中日英名称
+pub struct NameDetector;
+
+impl Detector for NameDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        let Some(end) = ctx.name_block_end else {
+            return Ok(());
+        };
+        let name_block = ctx.blocks[1..=end].join(" ");
+        let (name_en, name_zh, name_jp) = name_process(&name_block);
+        if name_en.is_some() || name_zh.is_some() || name_jp.is_some() {
+            ctx.add_confidence(20);
+        }
+        ctx.name_en = name_en;
+        ctx.name_zh = name_zh;
+        ctx.name_jp = name_jp;
+        Ok(())
+    }
+}
+
+/**
+ * 在name之后的块里寻找 语言sub, 清晰度res, 来源source
+ * 以及 视频编码, 色深, 音频编码, 容器, CRC32, 字幕类型, 这些不影响可信度
+ */
+pub struct TagDetector;
+
+impl Detector for TagDetector {
+    fn detect(&self, ctx: &mut Context) -> Result<()> {
+        let Some(end) = ctx.name_block_end else {
+            return Ok(());
+        };
+        let tag_blocks = ctx.blocks[end + 1..].to_vec();
+        let tag_block_iter = tag_blocks
+            .iter()
+            .flat_map(|s| s.split(' '))
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty());
+        let (sub, resolution, source) = find_tags_from_iter(tag_block_iter.clone());
+        for tag in [&sub, &resolution, &source] {
+            if tag.is_some() {
+                ctx.add_confidence(5);
+            }
+        }
+        ctx.sub = sub;
+        ctx.resolution = resolution;
+        ctx.source = source;
+
+        for e in tag_block_iter {
+            if ctx.video_codec.is_none() {
+                ctx.video_codec = first_capture(&VIDEO_CODEC_RE, e).map(normalize_video_codec);
+            }
+            if ctx.bit_depth.is_none() {
+                ctx.bit_depth = first_capture(&BIT_DEPTH_RE, e).and_then(|d| d.parse().ok());
+            }
+            if ctx.audio_codec.is_none() {
+                ctx.audio_codec = first_capture(&AUDIO_CODEC_RE, e).map(normalize_audio_codec);
+            }
+            if ctx.container.is_none() {
+                ctx.container = first_capture(&CONTAINER_RE, e).map(|c| c.to_lowercase());
+            }
+            if ctx.sub_type.is_none() {
+                ctx.sub_type = SUB_TYPE_RE.find(e).map(|m| match m.as_str() {
+                    "内嵌" | "內嵌" => SubType::Hard,
+                    "内封" | "內封" => SubType::Soft,
+                    _ => SubType::External,
+                });
+            }
+        }
+        // CRC32 一般是单独的块, 如 [ABCD1234], 全是数字的块更可能是日期
+        ctx.crc32 = tag_blocks
+            .iter()
+            .map(|s| s.trim())
+            .find(|s| CRC32_RE.is_match(s) && s.chars().any(|c| c.is_ascii_alphabetic()))
+            .map(|s| s.to_uppercase());
+        Ok(())
+    }
+}
+
+fn normalize_video_codec(codec: &str) -> String {
+    match codec.to_lowercase().replace('.', "").as_str() {
+        "avc" | "x264" | "h264" => "AVC".to_string(),
+        "hevc" | "x265" | "h265" => "HEVC".to_string(),
+        x => x.to_uppercase(),
+    }
+}
+
+fn normalize_audio_codec(codec: &str) -> String {
+    match codec.to_lowercase().as_str() {
+        "opus" => "Opus".to_string(),
+        "truehd" => "TrueHD".to_string(),
+        x => x.to_uppercase(),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Lang {
+    En,
+    Zh,
+    Jp,
+    Other,
+}
+
+fn find_lang(str: &str) -> Lang {
+    if JP_RE.is_match(str) {
+        Lang::Jp
+    } else if ZH_RE.is_match(str) {
+        Lang::Zh
+    } else if EN_RE.is_match(str) {
+        Lang::En
+    } else {
+        Lang::Other
+    }
+}
+
+// (name_en, name_zh, name_jp)
+fn name_process(name: &str) -> (Option<String>, Option<String>, Option<String>) {
+    let mut name_en = None;
+    let mut name_zh = None;
+    let mut name_jp = None;
+    // 去除仅限港澳台字样 僅限港澳台地區
+    let name_v1 = REGION_LIMIT_RE.replace_all(name.trim(), "");
+    // 用 / 或 两个空格 或 -跟两个空格 分割字符串
+    let split = NAME_SPLIT_RE
+        .split(&name_v1)
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<&str>>();
+    // 如果只有一个分割结果，那么就使用 _ 或 空格-空格 分割
+    let split = if split.len() == 1 {
+        if UNDERSCORE_RE.is_match(&name_v1) {
+            UNDERSCORE_RE.split(&name_v1).collect::<Vec<&str>>()
+        } else if SPACED_DASH_RE.is_match(&name_v1) {
+            SPACED_DASH_RE.split(&name_v1).collect::<Vec<&str>>()
+        } else {
+            split
+        }
+    } else {
+        split
+    };
+    // 如果分割结果只有一个，那么就用空格分割
+    let split = if split.len() == 1 {
+        split
+            .first()
+            .unwrap()
+            .split(' ')
+            .fold(Vec::<String>::new(), |mut acc, item| {
+                // 如果最后一个元素和当前元素的语言相同，那么就合并
+                match acc.last_mut() {
+                    Some(last) => {
+                        if find_lang(last) == find_lang(item) {
+                            *last = format!("{} {}", last, item);
+                        } else {
+                            acc.push(item.to_string());
+                        }
+                    }
+                    None => acc.push(item.to_string()),
+                };
+                acc
+            })
+    } else {
+        split.iter().map(|s| s.to_string()).collect()
+    };
+    // 处理分割结果
+    for item in split {
+        match find_lang(&item) {
+            Lang::En => {
+                if name_en.is_none() {
+                    name_en = Some(item.trim().to_owned())
+                }
+            }
+            Lang::Zh => {
+                if name_zh.is_none() {
+                    name_zh = Some(item.trim().to_owned())
+                }
+            }
+            Lang::Jp => {
+                if name_jp.is_none() {
+                    name_jp = Some(item.trim().to_owned())
+                }
+            }
+            Lang::Other => {}
+        }
+    }
+
+    (name_en, name_zh, name_jp)
+}
+
+fn find_tags_from_iter<'a, T>(iter: T) -> (Option<String>, Option<String>, Option<String>)
+where
+    T: IntoIterator<Item = &'a str>,
+{
+    let (mut sub, mut resolution, mut source) = (None, None, None);
+
+    for e in iter {
+        if sub.is_none() && SUB_RE.is_match(e) {
+            sub = Some(e.to_string());
+        } else if resolution.is_none() && RESOLUTION_RE.is_match(e) {
+            resolution = Some(e.to_string());
+        } else if source.is_none() && SOURCE_RE.is_match(e) {
+            source = Some(e.to_string());
+        }
+    }
+    // clean_sub
+    let sub = sub.map(|sub| CLEAN_SUB_RE.replace_all(&sub, "").to_string());
+
+    (sub, resolution, source)
+}
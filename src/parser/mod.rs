@@ -1,15 +1,28 @@
+#[cfg(test)]
+mod corpus;
+mod detect;
+mod subtitle;
+mod token;
+
+use std::fmt;
 use std::iter::Iterator;
+use std::ops::RangeInclusive;
 use std::sync::LazyLock;
-use std::{collections::HashMap, path};
 
 use color_eyre::eyre::{bail, eyre, Result};
-use lava_torrent::torrent::v1::Torrent;
-use regex::Regex;
+use regex::{Captures, Regex};
 
+use crate::config;
 use crate::VIDEO_EXTS;
-use crate::{
-    config,
-    dl::{self},
+
+pub use detect::{
+    find_season, BatchDetector, EpisodeInNameDetector, GroupDetector, NameDetector,
+    RegexEpisodeDetector, RegexSeasonDetector, SeasonDetector, StandaloneEpisodeDetector,
+    TagDetector,
+};
+pub use subtitle::{
+    link_external_subtitle, link_file, subtitle_stem, subtitle_track, SubtitleTrack, FONT_EXTS,
+    SUBTITLE_EXTS,
 };
 
 /*
@@ -20,321 +33,210 @@ use crate::{
    协议: MIT
 */
 
-static RESOLUTION_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"1080|720|2160|4K").unwrap());
-static SOURCE_RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"B-Global|[Bb]aha|[Bb]ilibili|AT-X|Web").unwrap());
-static SUB_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[简繁日字幕]|CH|BIG5|GB").unwrap());
-/*
-   这个正则表达式匹配所有不是字母、数字、下划线、空格、汉字、日文假名、片假名和连字符的字符。
-   其中，\w 匹配字母、数字和下划线；\s 匹配空格；\u4e00-\u9fff 匹配汉字；\u3040-\u309f 匹配日文假名；\u30a0-\u30ff 匹配片假名；- 匹配连字符。
-   [^\w\s\u4e00-\u9fff\u3040-\u309f\u30a0-\u30ff-] 表示匹配除了这些字符以外的所有字符
-*/
-static PREFIX_RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"[^\w\s\u4e00-\u9fff\u3040-\u309f\u30a0-\u30ff-]").unwrap());
-
-static CHINESE_NUMBER_MAP: LazyLock<HashMap<&'static str, u8>> = LazyLock::new(|| {
-    let mut map = HashMap::new();
-    map.insert("一", 1);
-    map.insert("二", 2);
-    map.insert("三", 3);
-    map.insert("四", 4);
-    map.insert("五", 5);
-    map.insert("六", 6);
-    map.insert("七", 7);
-    map.insert("八", 8);
-    map.insert("九", 9);
-    map.insert("十", 10);
-    map
-});
-
-#[derive(Debug, PartialEq)]
-enum Lang {
-    En,
-    Zh,
-    Jp,
-    Other,
+static TEMPLATE_PLACEHOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+static EMPTY_BRACKET_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\s*\]|\(\s*\)|【\s*】").unwrap());
+/// 只包含字段的括号, 字段都没有解析到时整个括号会被去掉
+static TAG_BRACKET_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\[(【]\s*(?:\{\w+\}\s*)+[\])】]").unwrap());
+/// 不设置 file_name_template 时的文件名, 和 [`link_file_name`] 一致
+const DEFAULT_FILE_NAME_TEMPLATE: &str = "{name} S{season}E{episode}";
+static DEFAULT_PARSER: LazyLock<Parser> = LazyLock::new(Parser::default);
+
+/// 解析过程中的中间状态, 由各个 [`Detector`] 依次读取和修改
+#[derive(Debug, Default)]
+pub struct Context {
+    /// 原始标题
+    pub title: String,
+    /// tokenizer 切分出的块, 第一块是group
+    pub blocks: Vec<String>,
+    pub group: Option<String>,
+    pub season: Option<u8>,
+    pub episode: Option<u32>,
+    /// 合集的最后一集, 此时 episode 为第一集
+    pub episode_end: Option<u32>,
+    /// 合集标记了 Fin / END / 完结
+    pub fin: bool,
+    /// name块结束的位置(包含), name块从第二块开始
+    pub name_block_end: Option<usize>,
+    pub name_en: Option<String>,
+    pub name_zh: Option<String>,
+    pub name_jp: Option<String>,
+    pub sub: Option<String>,
+    pub resolution: Option<String>,
+    pub source: Option<String>,
+    /// AVC HEVC AV1
+    pub video_codec: Option<String>,
+    pub bit_depth: Option<u8>,
+    /// AAC FLAC Opus ...
+    pub audio_codec: Option<String>,
+    /// mp4 mkv
+    pub container: Option<String>,
+    /// 大写的CRC32, 如 ABCD1234
+    pub crc32: Option<String>,
+    pub sub_type: Option<SubType>,
+    /// 0-100
+    pub confidence: u8,
 }
 
-fn find_lang(str: &str) -> Lang {
-    if Regex::new(r"[\u0800-\u4e00]{2,}").unwrap().is_match(str) {
-        Lang::Jp
-    } else if Regex::new(r"[\u4e00-\u9fa5]{2,}").unwrap().is_match(str) {
-        Lang::Zh
-    } else if Regex::new(r"[a-zA-Z]{2,}").unwrap().is_match(str) {
-        Lang::En
-    } else {
-        Lang::Other
+impl Context {
+    pub fn new(title: &str) -> Result<Context> {
+        Ok(Context {
+            title: title.to_string(),
+            blocks: token::tokenize(title)?,
+            ..Context::default()
+        })
     }
-}
 
-// (name_en, name_zh, name_jp)
-fn name_process(name: &str) -> (Option<String>, Option<String>, Option<String>) {
-    let mut name_en = None;
-    let mut name_zh = None;
-    let mut name_jp = None;
-    // 去除仅限港澳台字样 僅限港澳台地區
-    let name_v1 = Regex::new(r"[(（][仅僅]限港澳台地[区區][）)]")
-        .unwrap()
-        .replace_all(name.trim(), "");
-    // 用 / 或 两个空格 或 -跟两个空格 分割字符串
-    let split = Regex::new(r"/|\s{2}|-\s{2}")
-        .unwrap()
-        .split(&name_v1)
-        .filter(|s| !s.trim().is_empty())
-        .collect::<Vec<&str>>();
-    // 如果只有一个分割结果，那么就使用 _ 或 空格-空格 分割
-    let split = if split.len() == 1 {
-        if Regex::new("_{1}").unwrap().is_match(&name_v1) {
-            Regex::new(r"_")
-                .unwrap()
-                .split(&name_v1)
-                .collect::<Vec<&str>>()
-        } else if Regex::new(" - {1}").unwrap().is_match(&name_v1) {
-            Regex::new(r" - ")
-                .unwrap()
-                .split(&name_v1)
-                .collect::<Vec<&str>>()
-        } else {
-            split
-        }
-    } else {
-        split
-    };
-    // 如果分割结果只有一个，那么就用空格分割
-    let split = if split.len() == 1 {
-        split
-            .first()
-            .unwrap()
-            .split(' ')
-            .fold(Vec::<String>::new(), |mut acc, item| {
-                // 如果最后一个元素和当前元素的语言相同，那么就合并
-                match acc.last_mut() {
-                    Some(last) => {
-                        if find_lang(last) == find_lang(item) {
-                            *last = format!("{} {}", last, item);
-                        } else {
-                            acc.push(item.to_string());
-                        }
-                    }
-                    None => acc.push(item.to_string()),
-                };
-                acc
-            })
-    } else {
-        split.iter().map(|s| s.to_string()).collect()
-    };
-    // 处理分割结果
-    for item in split {
-        match find_lang(&item) {
-            Lang::En => {
-                if name_en.is_none() {
-                    name_en = Some(item.trim().to_owned())
-                }
-            }
-            Lang::Zh => {
-                if name_zh.is_none() {
-                    name_zh = Some(item.trim().to_owned())
-                }
-            }
-            Lang::Jp => {
-                if name_jp.is_none() {
-                    name_jp = Some(item.trim().to_owned())
-                }
-            }
-            Lang::Other => {}
-        }
+    pub fn add_confidence(&mut self, confidence: u8) {
+        self.confidence = self.confidence.saturating_add(confidence).min(100);
     }
+}
 
-    (name_en, name_zh, name_jp)
+pub trait Detector: Send + Sync {
+    fn detect(&self, ctx: &mut Context) -> Result<()>;
 }
 
-fn find_tags_from_iter<'a, T>(iter: T) -> (Option<String>, Option<String>, Option<String>)
-where
-    T: IntoIterator<Item = &'a str>,
-{
-    let (mut sub, mut resolution, mut source) = (None, None, None);
-
-    for e in iter {
-        if sub.is_none() && SUB_RE.is_match(e) {
-            sub = Some(e.to_string());
-        } else if resolution.is_none() && RESOLUTION_RE.is_match(e) {
-            resolution = Some(e.to_string());
-        } else if source.is_none() && SOURCE_RE.is_match(e) {
-            source = Some(e.to_string());
+/**
+ * 标题解析器, 先用tokenizer把标题切成块, 再依次运行detector
+ * 默认的detector顺序: group -> 合集 -> season -> 独立ep块 -> name中的ep -> name -> tags
+ */
+pub struct Parser {
+    detectors: Vec<Box<dyn Detector>>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser {
+            detectors: vec![
+                Box::new(GroupDetector),
+                Box::new(BatchDetector),
+                Box::new(SeasonDetector),
+                Box::new(StandaloneEpisodeDetector),
+                Box::new(EpisodeInNameDetector),
+                Box::new(NameDetector),
+                Box::new(TagDetector),
+            ],
         }
     }
-    // clean_sub
-    let sub = sub.map(|sub| {
-        Regex::new(r"_MP4|_MKV")
-            .unwrap()
-            .replace_all(&sub, "")
-            .to_string()
-    });
-
-    (sub, resolution, source)
 }
 
-/**
- * 处理单集标题
- * 标题格式: [group] name [season] [ep] [sub] [dpi] [source]
- * 可以在 name 里包含 season 和 ep
- * 独立ep块优先级更高
- * 在其他块里寻找 语言sub, 清晰度res, 来源source
- */
-pub fn process(title: &str) -> Result<Episode> {
-    let original_title = title;
-    //  1. 去除分割符号，分成多个小块
-    let title = title.trim().replace('【', "[").replace('】', "]");
-    let mut blocks = Regex::new(r"[\[\]]")
-        .unwrap()
-        .split(&title)
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .map(|s| s.to_string())
-        .collect::<Vec<String>>();
-    // 只有group和其他两块，把第二块重新分割
-    if blocks.len() == 2 {
-        let block_two = blocks.remove(1);
-        let split_reg = Regex::new(r"[ -]").unwrap();
-        blocks.extend(
-            split_reg
-                .split(&block_two)
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty())
-                .map(|s| s.to_string()),
-        );
+impl Parser {
+    pub fn new(detectors: Vec<Box<dyn Detector>>) -> Parser {
+        Parser { detectors }
     }
-    // 去除x月新番
-    let boring_block_reg1 = Regex::new(r"新番|月?番").unwrap();
-    blocks.retain(|s| {
-        let ss = PREFIX_RE.replace_all(s, "");
-        !(boring_block_reg1.is_match(&ss) && ss.len() <= 11)
-    });
-
-    // println!("{:?}", blocks);
-
-    // 3. 块1: group
-    let group = blocks.first().unwrap().to_string();
-
-    // [Season]
-    let mut maybe_season: Option<u8> = None;
-    let season_search_reg =
-        Regex::new(r"S(\d{1,2})|Season (\d{1,2})|[第 ](.)(?:部分|[季期部])").unwrap();
-
-    // ep
-    let ep_reg =
-        Regex::new(r"^(?:(\d+)|(\d+).?[vV](?<version>\d)|第?(\d+)[话話集]|(\d+).?END)$").unwrap();
-    let mut maybe_ep: Option<u32> = None;
-    let mut maybe_name_block_end_index: Option<usize> = None; // ep块的前一个块是name块 或者 ep包含在name块中
-
-    for (index, block) in blocks[1..].iter_mut().enumerate() {
-        // 没有season时尝试解析
-        if maybe_season.is_none() {
-            maybe_season = if let Some(Some(m)) = season_search_reg
-                .captures(block)
-                .and_then(|c| c.iter().skip(1).find(|s| s.is_some()))
-            {
-                match m.as_str().parse::<u8>() {
-                    Ok(season_int) => Some(season_int),
-                    Err(_) => CHINESE_NUMBER_MAP.get(m.as_str()).copied(),
-                }
-            } else {
-                None
-            };
 
-            // 去除Season信息
-            if maybe_season.is_some() {
-                *block = season_search_reg.replace_all(block, "").to_string();
-            }
+    /**
+     * 处理单集标题
+     * 标题格式: [group] name [season] [ep] [sub] [dpi] [source]
+     * 可以在 name 里包含 season 和 ep
+     * 独立ep块优先级更高
+     * 在其他块里寻找 语言sub, 清晰度res, 来源source
+     */
+    pub fn process(&self, title: &str) -> Result<Episode> {
+        let mut ctx = Context::new(title)?;
+        for detector in self.detectors.iter() {
+            detector.detect(&mut ctx)?;
         }
 
-        // 尝试解析ep
-        if maybe_ep.is_none() {
-            maybe_ep = if let Some(Some(s)) = ep_reg
-                .captures(block)
-                .and_then(|c| c.iter().skip(1).find(|s| s.is_some()))
-            {
-                Some(s.as_str().parse::<u32>().unwrap())
-            } else {
-                None
-            };
-
-            if maybe_ep.is_some() {
-                maybe_name_block_end_index = Some(index);
-            }
+        let Some(episode) = ctx.episode else {
+            return Ok(Episode::Sp {
+                name: title.to_string(),
+            });
+        };
+        if ctx.name_block_end.is_none() {
+            bail!("can't find name block for title: {}", title);
         }
+
+        let ep = Ep {
+            sub_group: ctx.group.unwrap_or_default(),
+            season: ctx.season.unwrap_or(1),
+            name_en: ctx.name_en,
+            name_zh: ctx.name_zh,
+            name_jp: ctx.name_jp,
+            episode,
+            sub: ctx.sub,
+            resolution: ctx.resolution,
+            source: ctx.source,
+            video_codec: ctx.video_codec,
+            bit_depth: ctx.bit_depth,
+            audio_codec: ctx.audio_codec,
+            container: ctx.container,
+            crc32: ctx.crc32,
+            sub_type: ctx.sub_type,
+            confidence: ctx.confidence,
+        };
+        Ok(match ctx.episode_end {
+            Some(end) => Episode::Batch(Batch {
+                ep,
+                end,
+                fin: ctx.fin,
+            }),
+            None => Episode::Ep(ep),
+        })
     }
+}
 
-    // 重新找，因为独立ep块的优先级高
-    if maybe_ep.is_none() {
-        let ep_from_name_reg =
-            Regex::new(r" -? ?(?:(\d+)|第(\d+)[话話集]|[Ee][Pp]?(\d+))(?:.?[vV](?<version>\d))?")
-                .unwrap();
-        for (index, block) in blocks[1..].iter_mut().enumerate() {
-            maybe_ep = if let Some(Some(s)) = ep_from_name_reg
-                .captures(block)
-                .and_then(|c| c.iter().skip(1).find(|s| s.is_some()))
-            {
-                Some(s.as_str().parse::<u32>().unwrap())
-            } else {
-                None
-            };
-
-            if maybe_ep.is_some() {
-                // 去除ep信息, 只保留第一个匹配之前的字符
-                let remain = block[ep_from_name_reg.find(block).unwrap().end()..].trim();
-                if !remain.is_empty() {
-                    // println!("remain:{}", &remain);
-                    bail!("can't understand episode number for title: {}", title)
-                }
-                *block = block[..ep_from_name_reg.find(block).unwrap().start()].to_string();
-                maybe_name_block_end_index = Some(index + 1);
-                break;
-            }
+/// 使用默认的 [`Parser`] 处理单集标题
+pub fn process(title: &str) -> Result<Episode> {
+    DEFAULT_PARSER.process(title)
+}
+
+/**
+ * 订阅中自定义的解析规则
+ * 先按 title_rewrite 依次改写标题, 再用 season_regex 和 episode_regex 代替内置的解析
+ * 自定义正则匹配不到时仍使用内置的解析
+ */
+pub struct TitleRules {
+    rewrites: Vec<(Regex, String)>,
+    parser: Parser,
+}
+
+impl TitleRules {
+    pub fn new(
+        episode_regex: Option<&str>,
+        season_regex: Option<&str>,
+        title_rewrite: &[config::TitleRewrite],
+    ) -> Result<TitleRules> {
+        let rewrites = title_rewrite
+            .iter()
+            .map(|r| Ok((Regex::new(&r.pattern)?, r.replace.clone())))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut detectors: Vec<Box<dyn Detector>> = vec![Box::new(GroupDetector)];
+        if let Some(re) = season_regex {
+            detectors.push(Box::new(RegexSeasonDetector(Regex::new(re)?)));
+        }
+        if let Some(re) = episode_regex {
+            detectors.push(Box::new(RegexEpisodeDetector(Regex::new(re)?)));
         }
+        detectors.push(Box::new(BatchDetector));
+        detectors.push(Box::new(SeasonDetector));
+        detectors.push(Box::new(StandaloneEpisodeDetector));
+        detectors.push(Box::new(EpisodeInNameDetector));
+        detectors.push(Box::new(NameDetector));
+        detectors.push(Box::new(TagDetector));
+
+        Ok(TitleRules {
+            rewrites,
+            parser: Parser::new(detectors),
+        })
     }
 
-    if maybe_ep.is_none() {
-        return Ok(Episode::Sp {
-            name: original_title.to_string(),
-        });
-    }
-    if maybe_name_block_end_index.is_none() {
-        bail!("can't find name block for title: {}", title);
+    pub fn process(&self, title: &str) -> Result<Episode> {
+        let mut title = title.to_string();
+        for (re, replace) in self.rewrites.iter() {
+            title = re.replace_all(&title, replace.as_str()).to_string();
+        }
+        self.parser.process(&title)
     }
-
-    let name_block_end_index = maybe_name_block_end_index.unwrap();
-
-    // name
-    let name_block = blocks[1..=name_block_end_index].join(" ");
-    // println!("{:?}, {:?}", name_block, maybe_name_block_end_index);
-
-    // 处理name
-    let (maybe_name_en, maybe_name_zh, maybe_name_jp) = name_process(&name_block);
-
-    let tag_block_iter = blocks[name_block_end_index + 1..]
-        .iter()
-        .flat_map(|s| s.split(' '))
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty());
-    let (sub, dpi, source) = find_tags_from_iter(tag_block_iter);
-
-    Ok(Episode::Ep(Ep {
-        sub_group: group,
-        season: maybe_season.unwrap_or(1),
-        name_en: maybe_name_en,
-        name_zh: maybe_name_zh,
-        name_jp: maybe_name_jp,
-        episode: maybe_ep.unwrap(),
-        sub,
-        resolution: dpi,
-        source,
-    }))
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Episode {
     Ep(Ep),
+    Batch(Batch),
     Sp { name: String },
 }
 
@@ -349,6 +251,97 @@ pub struct Ep {
     sub: Option<String>,
     resolution: Option<String>,
     source: Option<String>,
+    video_codec: Option<String>,
+    bit_depth: Option<u8>,
+    audio_codec: Option<String>,
+    container: Option<String>,
+    crc32: Option<String>,
+    sub_type: Option<SubType>,
+    confidence: u8,
+}
+
+/// 可以在 tag_filter 和 file_name_template 中使用的字段
+pub const EP_TAGS: [&str; 16] = [
+    "sub_group",
+    "season",
+    "episode",
+    "name_en",
+    "name_zh",
+    "name_jp",
+    "sub",
+    "resolution",
+    "source",
+    "video_codec",
+    "bit_depth",
+    "audio_codec",
+    "container",
+    "crc32",
+    "sub_type",
+    "confidence",
+];
+
+impl Ep {
+    pub fn crc32(&self) -> Option<&str> {
+        self.crc32.as_deref()
+    }
+
+    /// 按名字取字段的值, 名字见 [`EP_TAGS`], 没有解析到时为None
+    pub fn tag(&self, tag: &str) -> Option<String> {
+        match tag {
+            "sub_group" => Some(self.sub_group.clone()),
+            "season" => Some(format!("{:0>2}", self.season)),
+            "episode" => Some(self.episode.to_string()),
+            "name_en" => self.name_en.clone(),
+            "name_zh" => self.name_zh.clone(),
+            "name_jp" => self.name_jp.clone(),
+            "sub" => self.sub.clone(),
+            "resolution" => self.resolution.clone(),
+            "source" => self.source.clone(),
+            "video_codec" => self.video_codec.clone(),
+            "bit_depth" => self.bit_depth.map(|d| format!("{d}bit")),
+            "audio_codec" => self.audio_codec.clone(),
+            "container" => self.container.clone(),
+            "crc32" => self.crc32.clone(),
+            "sub_type" => self.sub_type.as_ref().map(|t| t.to_string()),
+            "confidence" => Some(self.confidence.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// 字幕类型
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SubType {
+    /// 内嵌
+    Hard,
+    /// 内封
+    Soft,
+    /// 外挂
+    External,
+}
+
+impl fmt::Display for SubType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SubType::Hard => "内嵌",
+            SubType::Soft => "内封",
+            SubType::External => "外挂",
+        })
+    }
+}
+
+/// 合集, ep.episode 为第一集
+#[derive(Debug, PartialEq)]
+pub struct Batch {
+    pub ep: Ep,
+    pub end: u32,
+    pub fin: bool,
+}
+
+impl Batch {
+    pub fn episodes(&self) -> RangeInclusive<u32> {
+        self.ep.episode..=self.end
+    }
 }
 
 impl Episode {
@@ -362,6 +355,11 @@ impl Episode {
     pub fn with_season(self, season: u8) -> Episode {
         match self {
             Episode::Ep(ep) => Episode::Ep(Ep { season, ..ep }),
+            Episode::Batch(Batch { ep, end, fin }) => Episode::Batch(Batch {
+                ep: Ep { season, ..ep },
+                end,
+                fin,
+            }),
             Episode::Sp { name } => Episode::Sp { name: name.clone() },
         }
     }
@@ -375,7 +373,7 @@ impl Episode {
                 Episode::Sp { name: sp_name } => {
                     name = Some(sp_name.clone());
                 }
-                Episode::Ep(ep) => {
+                Episode::Ep(ep) | Episode::Batch(Batch { ep, .. }) => {
                     if let Some(name_zh) = &ep.name_zh {
                         if name.is_none() {
                             name = Some(name_zh.clone());
@@ -401,7 +399,7 @@ impl Episode {
         link_path(
             name,
             match self {
-                Episode::Ep(ep) => &ep.season,
+                Episode::Ep(ep) | Episode::Batch(Batch { ep, .. }) => &ep.season,
                 Episode::Sp { .. } => &0,
             },
         )
@@ -410,6 +408,7 @@ impl Episode {
     pub fn link_file_name(&self, name: &str) -> String {
         match self {
             Episode::Ep(ep) => link_file_name(name, &ep.season, &ep.episode),
+            Episode::Batch(batch) => link_batch_file_name(name, &batch.ep.season, batch),
             Episode::Sp { name } => remove_video_ext_from(name),
         }
     }
@@ -417,13 +416,135 @@ impl Episode {
     pub fn link_file_name_with_season(&self, name: &str, season: &u8) -> String {
         match self {
             Episode::Ep(ep) => link_file_name(name, season, &ep.episode),
+            Episode::Batch(batch) => link_batch_file_name(name, season, batch),
             Episode::Sp { name } => remove_video_ext_from(name),
         }
     }
 
+    /**
+     * 按模板生成链接的文件名, 如 "{name} S{season}E{episode} [{resolution}][{video_codec}]"
+     * 除了 {name} 以外可以使用 [`EP_TAGS`] 中的字段, 合集的 {episode} 为 "1-E12"
+     * 没有解析到的字段替换为空, 并去掉因此留下的空括号和多余空格
+     * Sp 不使用模板
+     */
+    pub fn link_file_name_by_template(&self, template: &str, name: &str) -> String {
+        let (ep, episode) = match self {
+            Episode::Ep(ep) => (ep, ep.episode.to_string()),
+            Episode::Batch(batch) => (&batch.ep, format!("{}-E{}", batch.ep.episode, batch.end)),
+            Episode::Sp { name } => return remove_video_ext_from(name),
+        };
+        let file_name = TEMPLATE_PLACEHOLDER_RE.replace_all(template, |c: &Captures| match &c[1] {
+            "name" => name.to_string(),
+            "episode" => episode.clone(),
+            tag => ep.tag(tag).unwrap_or_default(),
+        });
+        let file_name = EMPTY_BRACKET_RE.replace_all(&file_name, "");
+        file_name.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /**
+     * 匹配按模板生成的链接文件名(不带扩展名), 用于从链接目录中找到这一集已经链接的视频
+     * {name} {season} {episode} 以外的字段来自视频的标题, 可以是任意内容或为空
+     * 紧跟在 {episode} 后面的字段不能以数字开头, 避免 E1 匹配到 E10
+     */
+    pub fn link_file_name_regex(&self, template: Option<&str>, name: &str) -> Regex {
+        let (ep, episode) = match self {
+            Episode::Ep(ep) => (ep, ep.episode.to_string()),
+            Episode::Batch(batch) => (&batch.ep, format!("{}-E{}", batch.ep.episode, batch.end)),
+            Episode::Sp { name } => {
+                return Regex::new(&format!(
+                    "^{}$",
+                    regex::escape(&remove_video_ext_from(name))
+                ))
+                .unwrap()
+            }
+        };
+        let template = template.unwrap_or(DEFAULT_FILE_NAME_TEMPLATE);
+        // 只有其他字段的括号可能整个不存在, 当作一个字段
+        let template = TAG_BRACKET_RE.replace_all(template, |c: &Captures| {
+            let fixed = TEMPLATE_PLACEHOLDER_RE
+                .captures_iter(&c[0])
+                .any(|p| ["name", "season", "episode"].contains(&&p[1]));
+            if fixed {
+                c[0].to_string()
+            } else {
+                "{_}".to_string()
+            }
+        });
+
+        let mut pattern = "^".to_string();
+        let mut after_episode = false;
+        let push_literal = |pattern: &mut String, literal: &str, after_episode: &mut bool| {
+            // 生成文件名时合并了空格
+            let parts = literal.split_whitespace().map(regex::escape);
+            pattern.push_str(r"\s*");
+            pattern.push_str(&parts.collect::<Vec<_>>().join(r"\s*"));
+            pattern.push_str(r"\s*");
+            if !literal.trim().is_empty() {
+                *after_episode = false;
+            }
+        };
+        let mut last = 0;
+        for c in TEMPLATE_PLACEHOLDER_RE.captures_iter(&template) {
+            let m = c.get(0).unwrap();
+            push_literal(&mut pattern, &template[last..m.start()], &mut after_episode);
+            last = m.end();
+            match &c[1] {
+                "name" => pattern.push_str(&regex::escape(name)),
+                "season" => pattern.push_str(&regex::escape(&ep.tag("season").unwrap_or_default())),
+                "episode" => {
+                    pattern.push_str(&regex::escape(&episode));
+                    after_episode = true;
+                    continue;
+                }
+                _ if after_episode => {
+                    pattern.push_str(r"(?:\D.*)?");
+                    continue;
+                }
+                _ => pattern.push_str(".*"),
+            }
+            after_episode = false;
+        }
+        push_literal(&mut pattern, &template[last..], &mut after_episode);
+        pattern.push('$');
+        Regex::new(&pattern).unwrap()
+    }
+
+    /// Sp 为0
+    pub fn season(&self) -> u8 {
+        match self {
+            Episode::Ep(ep) | Episode::Batch(Batch { ep, .. }) => ep.season,
+            Episode::Sp { .. } => 0,
+        }
+    }
+
+    pub fn crc32(&self) -> Option<&str> {
+        match self {
+            Episode::Ep(ep) | Episode::Batch(Batch { ep, .. }) => ep.crc32(),
+            Episode::Sp { .. } => None,
+        }
+    }
+
+    /// 解析结果的可信度(0-100), 没有找到ep的Sp为0
+    pub fn confidence(&self) -> u8 {
+        match self {
+            Episode::Ep(ep) | Episode::Batch(Batch { ep, .. }) => ep.confidence,
+            Episode::Sp { .. } => 0,
+        }
+    }
+
+    /// 修正后小于0的集数为0
     pub fn revise_ep(&mut self, ep_revise: &i8) {
-        if let Episode::Ep(ep) = self {
-            ep.episode = (ep.episode as i32 + *ep_revise as i32) as u32;
+        let revise = |episode: u32| episode.saturating_add_signed(*ep_revise as i32);
+        match self {
+            Episode::Ep(ep) => {
+                ep.episode = revise(ep.episode);
+            }
+            Episode::Batch(Batch { ep, end, .. }) => {
+                ep.episode = revise(ep.episode);
+                *end = revise(*end);
+            }
+            Episode::Sp { .. } => {}
         }
     }
 }
@@ -445,90 +566,11 @@ pub fn link_file_name(name: &str, season: &u8, episode: &u32) -> String {
     format!("{} S{:0>2}E{}", &name, &season, &episode)
 }
 
-const SUBTITLE_EXTS: [&str; 2] = ["srt", "ass"];
-
-pub fn link_external_subtitle(
-    torrent: &Torrent,
-    file_stem: &str,
-    full_path: &str,
-    link_file_name: &str,
-    link_config: &config::Link,
-    server_torrent: &dl::Torrent,
-) -> Result<()> {
-    let subtitle_reg = Regex::new(r"[._](.*)").unwrap();
-    for file in torrent.files.as_ref().unwrap() {
-        let file_suffix = file.path.extension().unwrap().to_str().unwrap();
-        let file_name_from_torrent = file.path.file_name().unwrap().to_str().unwrap();
-        if SUBTITLE_EXTS.iter().any(|ext| ext == &file_suffix) {
-            let mut lan = None;
-            if file_name_from_torrent.starts_with(file_stem) {
-                let subtitle_block = file_name_from_torrent
-                    .replace(file_stem, "")
-                    .replace(&format!(".{file_suffix}"), "")
-                    .to_lowercase();
-                if let Some(c) = subtitle_reg
-                    .captures(&subtitle_block)
-                    .and_then(|c| c.get(1))
-                {
-                    lan = match c.as_str() {
-                        "tc" | "zh-hant" => Some("zh-HK".to_string()),
-                        "sc" | "zh-hans" | "" => Some("zh".to_string()),
-                        "ja" => Some("ja".to_string()),
-                        x => Some(x.to_string()),
-                    };
-                }
-            } else {
-                let zh_hints = ["简中", "簡中"];
-                let zh_hk_hints = ["繁中"];
-                let ja_hints = ["日文", "日语", "日語", "日本语", "日本語"];
-                if zh_hints
-                    .iter()
-                    .any(|hint| file_name_from_torrent.contains(hint))
-                {
-                    lan = Some("zh".to_string());
-                } else if zh_hk_hints
-                    .iter()
-                    .any(|hint| file_name_from_torrent.contains(hint))
-                {
-                    lan = Some("zh-HK".to_string());
-                } else if ja_hints
-                    .iter()
-                    .any(|hint| file_name_from_torrent.contains(hint))
-                {
-                    lan = Some("ja".to_string());
-                }
-            }
-            if let Some(lan) = lan {
-                let link = format!("{full_path}/{link_file_name}.{lan}.{file_suffix}");
-                if !path::Path::new(&link).exists() {
-                    if link_config.dry_run {
-                        println!(
-                            "准备字幕链接{link} <- {}/{file_name_from_torrent}",
-                            &torrent.name
-                        );
-                    } else {
-                        std::fs::create_dir_all(full_path)?;
-                        match std::fs::hard_link(
-                            format!(
-                                "{}/{}/{file_name_from_torrent}",
-                                &server_torrent.download_dir, &torrent.name
-                            ),
-                            &link,
-                        ) {
-                            Ok(_) => {
-                                println!(
-                                    "创建字幕链接{link} <- {}/{file_name_from_torrent}",
-                                    &torrent.name
-                                );
-                            }
-                            Err(e) => println!("硬链接失败: {}", e),
-                        }
-                    }
-                }
-            }
-        }
-    }
-    Ok(())
+fn link_batch_file_name(name: &str, season: &u8, batch: &Batch) -> String {
+    format!(
+        "{} S{:0>2}E{}-E{}",
+        &name, &season, &batch.ep.episode, &batch.end
+    )
 }
 
 #[cfg(test)]
@@ -542,6 +584,125 @@ mod tests {
         assert_eq!(name, remove_video_ext_from(&format!("{name}.mp4")));
     }
 
+    #[test]
+    fn test_media_tags() {
+        let ep = process("[Nekomoe kissaten&LoliHouse] Sousou no Frieren - 01 [WebRip 1080p HEVC-10bit AAC ASSx2][简繁内封字幕][ABCD1234].mkv")
+            .unwrap()
+            .unwrap_ep();
+        assert_eq!(ep.tag("video_codec").as_deref(), Some("HEVC"));
+        assert_eq!(ep.tag("bit_depth").as_deref(), Some("10bit"));
+        assert_eq!(ep.tag("audio_codec").as_deref(), Some("AAC"));
+        assert_eq!(ep.tag("container").as_deref(), Some("mkv"));
+        assert_eq!(ep.crc32(), Some("ABCD1234"));
+        assert_eq!(ep.sub_type, Some(SubType::Soft));
+
+        let ep = process(
+            "[VCB-Studio] Kage no Jitsuryokusha ni Naritakute! [01][Ma10p_1080p][x265_flac].mkv",
+        )
+        .unwrap()
+        .unwrap_ep();
+        assert_eq!(ep.video_codec.as_deref(), Some("HEVC"));
+        assert_eq!(ep.bit_depth, Some(10));
+        assert_eq!(ep.audio_codec.as_deref(), Some("FLAC"));
+
+        let ep = process("[SubsPlease] Sousou no Frieren - 01 (1080p) [F02B9CEE].mkv")
+            .unwrap()
+            .unwrap_ep();
+        assert_eq!(ep.episode, 1);
+        assert_eq!(ep.resolution.as_deref(), Some("1080p"));
+        assert_eq!(ep.crc32(), Some("F02B9CEE"));
+    }
+
+    #[test]
+    fn test_link_file_name_by_template() {
+        let ep =
+            process("[ANi] 葬送的芙莉蓮 - 01 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]").unwrap();
+        assert_eq!(
+            ep.link_file_name_by_template(
+                "{name} S{season}E{episode} [{resolution}][{video_codec}]",
+                "芙莉莲"
+            ),
+            "芙莉莲 S01E1 [1080P][AVC]"
+        );
+        // 没有解析到的字段
+        assert_eq!(
+            ep.link_file_name_by_template(
+                "{name} S{season}E{episode} [{crc32}] ({sub_type})",
+                "芙莉莲"
+            ),
+            "芙莉莲 S01E1"
+        );
+    }
+
+    #[test]
+    fn test_link_file_name_regex() {
+        // 字幕的标题中没有分辨率等字段
+        let ep = process("[LoliHouse] 孤独摇滚 - 01 [简体内嵌]").unwrap();
+        let template = "{name} S{season}E{episode} [{resolution}][{video_codec}]";
+        let re = ep.link_file_name_regex(Some(template), "孤独摇滚");
+        assert!(re.is_match("孤独摇滚 S01E1 [1080p][HEVC]"));
+        assert!(re.is_match("孤独摇滚 S01E1 [1080p]"));
+        assert!(re.is_match("孤独摇滚 S01E1"));
+        assert!(!re.is_match("孤独摇滚 S01E10 [1080p]"));
+        assert!(!re.is_match("孤独摇滚 S01E10"));
+        assert!(!re.is_match("孤独摇滚 S02E1"));
+
+        // 字段在前面, 和生成的文件名一致
+        let template = "[{sub_group}] {name} - S{season}E{episode}";
+        let video =
+            process("[ANi] 孤独摇滚 - 01 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]").unwrap();
+        let re = ep.link_file_name_regex(Some(template), "孤独摇滚");
+        assert!(re.is_match(&video.link_file_name_by_template(template, "孤独摇滚")));
+        assert!(!re.is_match("[ANi] 孤独摇滚 - S01E11"));
+
+        let re = ep.link_file_name_regex(None, "孤独摇滚");
+        assert!(re.is_match(&ep.link_file_name("孤独摇滚")));
+        assert!(!re.is_match("孤独摇滚 S01E1 [1080p]"));
+    }
+
+    #[test]
+    fn test_title_rules() {
+        // 跨块的正则在整个标题上匹配
+        let rules = TitleRules::new(Some(r"\]\[(\d{1,3})\]\["), None, &[]).unwrap();
+        let ep = rules
+            .process("[GM-Team][国漫][仙逆][Renegade Immortal][2023][15][AVC][GB][1080P]")
+            .unwrap()
+            .unwrap_ep();
+        assert_eq!(ep.episode, 15);
+
+        let rules = TitleRules::new(
+            None,
+            Some(r"第(\d+)部"),
+            &[config::TitleRewrite {
+                pattern: r"\[2023\]".to_string(),
+                replace: "".to_string(),
+            }],
+        )
+        .unwrap();
+        let ep = rules
+            .process("[GM-Team][国漫][仙逆 第2部][Renegade Immortal][2023][15][AVC][GB][1080P]")
+            .unwrap()
+            .unwrap_ep();
+        assert_eq!(ep.episode, 15);
+        assert_eq!(ep.season, 2);
+        assert_eq!(ep.name_en, Some("Renegade Immortal".to_string()));
+
+        assert!(TitleRules::new(Some("("), None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_revise_ep() {
+        let rules = TitleRules::new(None, None, &[]).unwrap();
+        let title = "[ANi] 葬送的芙莉蓮 - 03 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]";
+        let mut ep = rules.process(title).unwrap();
+        ep.revise_ep(&-2);
+        assert_eq!(ep.unwrap_ep().episode, 1);
+        // 不会溢出成很大的集数
+        let mut ep = rules.process(title).unwrap();
+        ep.revise_ep(&-48);
+        assert_eq!(ep.unwrap_ep().episode, 0);
+    }
+
     #[test]
     fn test_parser() {
         let ep = process("[Up to 21°C] 擅长逃跑的殿下 / Nige Jouzu no Wakagimi - 9.5 (Baha 1920x1080 AVC AAC MP4)");
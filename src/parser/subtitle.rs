@@ -0,0 +1,335 @@
+use std::{ffi::OsStr, fs, path::Path, sync::LazyLock};
+
+use color_eyre::eyre::{eyre, Result};
+use lava_torrent::torrent::v1::Torrent;
+use regex::Regex;
+
+use crate::{
+    config::{self, SubtitleLang},
+    dl,
+};
+
+pub const SUBTITLE_EXTS: [&str; 7] = ["srt", "ass", "ssa", "vtt", "sup", "idx", "sub"];
+pub const FONT_EXTS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+static SUBTITLE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[._](.*)").unwrap());
+static DEFAULT_SUBTITLE_LANGS: LazyLock<Vec<SubtitleLang>> = LazyLock::new(|| {
+    vec![
+        subtitle_lang(
+            "zh",
+            &["", "sc", "chs", "zh-hans", "gb"],
+            &["简中", "簡中", "简体", "簡體"],
+        ),
+        subtitle_lang(
+            "zh-HK",
+            &["tc", "cht", "zh-hant", "big5"],
+            &["繁中", "繁体", "繁體"],
+        ),
+        subtitle_lang(
+            "ja",
+            &["ja", "jp", "jpn"],
+            &["日文", "日语", "日語", "日本语", "日本語"],
+        ),
+    ]
+});
+
+fn subtitle_lang(lang: &str, suffixes: &[&str], hints: &[&str]) -> SubtitleLang {
+    SubtitleLang {
+        lang: lang.to_string(),
+        suffixes: suffixes.iter().map(|s| s.to_string()).collect(),
+        hints: hints.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/// 外挂字幕的语言和标记
+#[derive(Debug, PartialEq)]
+pub struct SubtitleTrack {
+    pub lang: String,
+    pub forced: bool,
+    pub default: bool,
+}
+
+impl SubtitleTrack {
+    /// 如 `某番剧 S01E1.zh.default.ass`
+    pub fn link_file_name(&self, link_file_name: &str, ext: &str) -> String {
+        let mut name = format!("{link_file_name}.{}", self.lang);
+        if self.default {
+            name.push_str(".default");
+        }
+        if self.forced {
+            name.push_str(".forced");
+        }
+        format!("{name}.{ext}")
+    }
+}
+
+/**
+ * 根据字幕文件名判断语言, 判断不出时返回None
+ * 1. 以视频文件名开头时, 用剩下的部分匹配 suffixes, 匹配不到时直接作为语言
+ * 2. 否则在文件名中寻找 hints
+ *
+ * 配置中的 subtitle_langs 优先于内置的识别表, default_subtitle_lang 的字幕标记为 default
+ */
+pub fn subtitle_track(
+    file_name: &str,
+    video_file_stem: &str,
+    link_config: &config::Link,
+) -> Option<SubtitleTrack> {
+    let ext = file_name.rsplit('.').next()?;
+    let langs = || {
+        link_config
+            .subtitle_langs
+            .iter()
+            .chain(DEFAULT_SUBTITLE_LANGS.iter())
+    };
+    let lower_file_name = file_name.to_lowercase();
+
+    let from_suffix = file_name
+        .strip_prefix(video_file_stem)
+        .and_then(|rest| rest.strip_suffix(ext))
+        .and_then(|rest| {
+            let rest = rest.trim_end_matches('.').to_lowercase();
+            let block = SUBTITLE_RE.captures(&rest)?.get(1)?.as_str().to_string();
+            Some(block)
+        })
+        .map(|block| {
+            let mut tokens = block.split('.').collect::<Vec<_>>();
+            let forced = tokens.contains(&"forced");
+            let default = tokens.contains(&"default");
+            tokens.retain(|t| *t != "forced" && *t != "default");
+            let key = tokens.join(".");
+            let lang = langs()
+                .find(|l| l.suffixes.iter().any(|s| s.to_lowercase() == key))
+                .map(|l| l.lang.clone())
+                .unwrap_or(key);
+            (lang, forced, default)
+        });
+    let (lang, forced, default) = match from_suffix {
+        Some(track) => track,
+        None => {
+            let lang = langs()
+                .find(|l| {
+                    l.hints
+                        .iter()
+                        .any(|h| lower_file_name.contains(&h.to_lowercase()))
+                })?
+                .lang
+                .clone();
+            (
+                lang,
+                lower_file_name.contains("forced"),
+                lower_file_name.contains("default"),
+            )
+        }
+    };
+
+    let default = default || link_config.default_subtitle_lang.as_ref() == Some(&lang);
+    Some(SubtitleTrack {
+        lang,
+        forced,
+        default,
+    })
+}
+
+/**
+ * 没有对应视频文件时, 猜测字幕文件名中视频名的部分
+ * 去掉扩展名后, 末尾只有字母的短 .xx 段视为语言和标记, 如 `Name [01].tc.forced.ass` -> `Name [01]`
+ */
+pub fn subtitle_stem(file_name: &str) -> &str {
+    let mut stem = file_name.rsplit_once('.').map_or(file_name, |(s, _)| s);
+    while let Some((rest, segment)) = stem.rsplit_once('.') {
+        if segment.is_empty()
+            || segment.len() > 10
+            || !segment
+                .chars()
+                .all(|c| c.is_ascii_alphabetic() || c == '-' || c == '_')
+        {
+            break;
+        }
+        stem = rest;
+    }
+    stem
+}
+
+/**
+ * 链接种子中和视频对应的外挂字幕到视频旁边
+ * 开启 collect_fonts 时, 种子中的字体文件链接到 {path}/{name}/fonts
+ */
+pub fn link_external_subtitle(
+    torrent: &Torrent,
+    file_stem: &str,
+    full_path: &str,
+    link_file_name: &str,
+    link_config: &config::Link,
+    server_torrent: &dl::Torrent,
+) -> Result<()> {
+    let Some(files) = &torrent.files else {
+        return Ok(());
+    };
+    for file in files {
+        let Some(file_suffix) = file.path.extension().and_then(OsStr::to_str) else {
+            continue;
+        };
+        let file_suffix = file_suffix.to_lowercase();
+        let file_name_from_torrent = file
+            .path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .ok_or_else(|| eyre!("get file_name & to_str failed: {:?}", file.path))?;
+        let original = format!(
+            "{}/{}/{}",
+            &server_torrent.download_dir,
+            &torrent.name,
+            file.path
+                .to_str()
+                .ok_or_else(|| eyre!("get path & to_str failed: {:?}", file.path))?
+        );
+
+        if FONT_EXTS.contains(&file_suffix.as_str()) {
+            if link_config.collect_fonts {
+                if let Some(series_path) = Path::new(full_path).parent() {
+                    let link = series_path.join("fonts").join(file_name_from_torrent);
+                    link_file(&original, &link, link_config.dry_run, "字体")?;
+                }
+            }
+            continue;
+        }
+
+        if !SUBTITLE_EXTS.contains(&file_suffix.as_str()) {
+            continue;
+        }
+        if let Some(track) = subtitle_track(file_name_from_torrent, file_stem, link_config) {
+            let link =
+                Path::new(full_path).join(track.link_file_name(link_file_name, &file_suffix));
+            link_file(&original, &link, link_config.dry_run, "字幕")?;
+        }
+    }
+    Ok(())
+}
+
+pub fn link_file(original: &str, link: &Path, dry_run: bool, kind: &str) -> Result<()> {
+    if link.exists() {
+        return Ok(());
+    }
+    if dry_run {
+        println!("准备{kind}链接{} <- {original}", link.display());
+        return Ok(());
+    }
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::hard_link(original, link) {
+        Ok(_) => println!("创建{kind}链接{} <- {original}", link.display()),
+        Err(e) => println!("硬链接失败: {}", e),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link_config(extra: &str) -> config::Link {
+        toml::from_str(&format!("enable = true\npath = \"/link\"\n{extra}")).unwrap()
+    }
+
+    fn track(lang: &str, forced: bool, default: bool) -> Option<SubtitleTrack> {
+        Some(SubtitleTrack {
+            lang: lang.to_string(),
+            forced,
+            default,
+        })
+    }
+
+    #[test]
+    fn test_subtitle_track() {
+        let link = link_config("");
+        let stem = "[Nekomoe kissaten][Bocchi the Rock!][01][1080p]";
+        assert_eq!(
+            subtitle_track(&format!("{stem}.tc.ass"), stem, &link),
+            track("zh-HK", false, false)
+        );
+        assert_eq!(
+            subtitle_track(&format!("{stem}.chs.ssa"), stem, &link),
+            track("zh", false, false)
+        );
+        assert_eq!(
+            subtitle_track(&format!("{stem}.en.forced.vtt"), stem, &link),
+            track("en", true, false)
+        );
+        assert_eq!(subtitle_track(&format!("{stem}.ass"), stem, &link), None);
+        assert_eq!(
+            subtitle_track(
+                "[Sub] Bocchi [01][简中].ass",
+                "[Sub] Bocchi [01][简中]",
+                &link
+            ),
+            track("zh", false, false)
+        );
+        assert_eq!(
+            subtitle_track("[VCB-Studio] Bocchi [01][简中].idx", stem, &link),
+            track("zh", false, false)
+        );
+        assert_eq!(
+            subtitle_track("[VCB-Studio] Bocchi [01][日文].sup", stem, &link),
+            track("ja", false, false)
+        );
+        assert_eq!(subtitle_track("readme.srt", stem, &link), None);
+
+        let link = link_config(
+            r#"
+            subtitle_langs = [{ lang = "zh-CN", suffixes = ["jpsc", "sc"], hints = ["简日"] }]
+            default_subtitle_lang = "zh-CN"
+            "#,
+        );
+        assert_eq!(
+            subtitle_track(&format!("{stem}.JPSC.ass"), stem, &link),
+            track("zh-CN", false, true)
+        );
+        assert_eq!(
+            subtitle_track(&format!("{stem}.sc.ass"), stem, &link),
+            track("zh-CN", false, true)
+        );
+        assert_eq!(
+            subtitle_track("Bocchi [01][简日双语].srt", stem, &link),
+            track("zh-CN", false, true)
+        );
+        assert_eq!(
+            subtitle_track(&format!("{stem}.tc.ass"), stem, &link),
+            track("zh-HK", false, false)
+        );
+    }
+
+    #[test]
+    fn test_subtitle_stem() {
+        assert_eq!(
+            subtitle_stem("[Sub] Bocchi the Rock! [01][1080p].tc.forced.ass"),
+            "[Sub] Bocchi the Rock! [01][1080p]"
+        );
+        assert_eq!(
+            subtitle_stem("[Sub] Dr.Stone [01].ass"),
+            "[Sub] Dr.Stone [01]"
+        );
+        assert_eq!(subtitle_stem("Bocchi [01][简中].srt"), "Bocchi [01][简中]");
+        assert_eq!(
+            subtitle_stem("Bocchi.S01E01.1080p.zh-Hans.srt"),
+            "Bocchi.S01E01.1080p"
+        );
+    }
+
+    #[test]
+    fn test_subtitle_link_file_name() {
+        assert_eq!(
+            track("zh", true, true)
+                .unwrap()
+                .link_file_name("孤独摇滚 S01E1", "ass"),
+            "孤独摇滚 S01E1.zh.default.forced.ass"
+        );
+        assert_eq!(
+            track("ja", false, false)
+                .unwrap()
+                .link_file_name("孤独摇滚 S01E1", "sup"),
+            "孤独摇滚 S01E1.ja.sup"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::LazyLock;
+
+use color_eyre::eyre::{bail, Result};
+use regex::Regex;
+
+static BRACKET_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\[\]]").unwrap());
+static SPACE_OR_DASH_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[ -]").unwrap());
+static BORING_BLOCK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"新番|月?番").unwrap());
+/*
+   这个正则表达式匹配所有不是字母、数字、下划线、空格、汉字、日文假名、片假名和连字符的字符。
+   其中，\w 匹配字母、数字和下划线；\s 匹配空格；\u4e00-\u9fff 匹配汉字；\u3040-\u309f 匹配日文假名；\u30a0-\u30ff 匹配片假名；- 匹配连字符。
+   [^\w\s\u4e00-\u9fff\u3040-\u309f\u30a0-\u30ff-] 表示匹配除了这些字符以外的所有字符
+*/
+static PREFIX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[^\w\s\u4e00-\u9fff\u3040-\u309f\u30a0-\u30ff-]").unwrap());
+
+/**
+ * 把标题切分成块
+ * 1. 统一括号后按 [] 分割
+ * 2. 只有group和其他一块时, 把第二块按空格和-重新分割
+ * 3. 去除"x月新番"之类的无用块
+ */
+pub fn tokenize(title: &str) -> Result<Vec<String>> {
+    let title = title.trim().replace('【', "[").replace('】', "]");
+    let mut blocks = BRACKET_RE
+        .split(&title)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+    if blocks.len() == 2 {
+        let block_two = blocks.remove(1);
+        blocks.extend(
+            SPACE_OR_DASH_RE
+                .split(&block_two)
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+        );
+    }
+    blocks.retain(|s| {
+        let ss = PREFIX_RE.replace_all(s, "");
+        !(BORING_BLOCK_RE.is_match(&ss) && ss.len() <= 11)
+    });
+
+    if blocks.is_empty() {
+        bail!("nothing to parse in title: {}", title)
+    }
+
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("【喵萌奶茶屋】★04月新番★[夏日重现/Summer Time Rendering][11][1080p]")
+                .unwrap(),
+            vec![
+                "喵萌奶茶屋",
+                "夏日重现/Summer Time Rendering",
+                "11",
+                "1080p"
+            ]
+        );
+        assert_eq!(
+            tokenize("[ANi] Frieren - 01 ").unwrap(),
+            vec!["ANi", "Frieren", "01"]
+        );
+        assert!(tokenize(" [] ").is_err());
+    }
+}
@@ -1,9 +1,11 @@
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
-use color_eyre::eyre::{eyre, Result};
+use color_eyre::eyre::{bail, eyre, Result};
 use data_encoding::{BASE32, HEXLOWER};
 use regex::Regex;
 
+use crate::config::Rule;
+
 mod dmhy;
 
 pub fn get_res_api(res_config: &crate::config::ResApi) -> impl ApiServer {
@@ -27,6 +29,63 @@ pub trait ApiServer {
     ) -> Result<(Vec<Res>, bool)>;
 }
 
+/**
+ * 检查 rule 中的字幕组和资源类型在资源站中存在
+ * 只填了id或名字时, 补全另一个
+ */
+pub async fn validate_rule(rule: &mut Rule, api: &impl ApiServer) -> Result<()> {
+    if rule.name.trim().is_empty() {
+        bail!("rule的name不能为空");
+    }
+    if rule.keywords.iter().all(|k| k.trim().is_empty()) {
+        bail!("rule的keywords不能为空");
+    }
+    if rule.sub_group_id.is_some() || rule.sub_group_name.is_some() {
+        let (id, name) = find_option(
+            &api.sub_groups().await?,
+            rule.sub_group_id,
+            rule.sub_group_name.as_deref(),
+        )
+        .ok_or_else(|| {
+            eyre!(
+                "字幕组不存在: {:?} {:?}",
+                rule.sub_group_id,
+                rule.sub_group_name
+            )
+        })?;
+        rule.sub_group_id = Some(id);
+        rule.sub_group_name = Some(name);
+    }
+    if rule.res_type_id.is_some() || rule.res_type_name.is_some() {
+        let (id, name) = find_option(
+            &api.res_types().await?,
+            rule.res_type_id,
+            rule.res_type_name.as_deref(),
+        )
+        .ok_or_else(|| {
+            eyre!(
+                "资源类型不存在: {:?} {:?}",
+                rule.res_type_id,
+                rule.res_type_name
+            )
+        })?;
+        rule.res_type_id = Some(id);
+        rule.res_type_name = Some(name);
+    }
+    Ok(())
+}
+
+fn find_option(
+    options: &[(i32, String)],
+    id: Option<i32>,
+    name: Option<&str>,
+) -> Option<(i32, String)> {
+    options
+        .iter()
+        .find(|(i, n)| id.is_none_or(|id| id == *i) && name.is_none_or(|name| name == n))
+        .cloned()
+}
+
 pub struct Res {
     pub title: String,
     pub api: crate::config::ResApi,
@@ -69,9 +128,57 @@ fn get_info_hash_from_magnet(magnet: &str) -> Result<String> {
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
     use chrono::prelude::*;
 
+    struct FakeApi;
+
+    #[async_trait]
+    impl ApiServer for FakeApi {
+        async fn sub_groups(&self) -> Result<Vec<(i32, String)>> {
+            Ok(vec![
+                (604, "c.c".to_string()),
+                (619, "桜都字幕组".to_string()),
+            ])
+        }
+        async fn res_types(&self) -> Result<Vec<(i32, String)>> {
+            Ok(vec![(2, "动画".to_string())])
+        }
+        async fn res_list(
+            &self,
+            _keywords: &[String],
+            _subgroup_id: Option<i32>,
+            _res_type_id: Option<i32>,
+            _publish_after: Option<NaiveDateTime>,
+        ) -> Result<(Vec<Res>, bool)> {
+            Ok((vec![], false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_rule() {
+        let mut rule: Rule = toml::from_str(
+            r#"
+            name = "约"
+            keywords = ["约"]
+            res_api = "dmhy"
+            sub_group_name = "c.c"
+            res_type_id = 2
+            "#,
+        )
+        .unwrap();
+        validate_rule(&mut rule, &FakeApi).await.unwrap();
+        assert_eq!(rule.sub_group_id, Some(604));
+        assert_eq!(rule.res_type_name, Some("动画".to_string()));
+
+        rule.sub_group_id = Some(619);
+        assert!(validate_rule(&mut rule, &FakeApi).await.is_err());
+        rule.sub_group_id = None;
+        rule.sub_group_name = None;
+        rule.keywords = vec![];
+        assert!(validate_rule(&mut rule, &FakeApi).await.is_err());
+    }
+
     #[test]
     fn test_time_compare() {
         let time1 = Local
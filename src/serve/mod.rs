@@ -1,23 +1,42 @@
-use std::collections::HashMap;
+mod tls;
+mod v1;
+
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
-    checker::{check_everything, LAST_CHECK_RESULT},
-    config::{Collection, Config, Mikan},
+    checker::{check_everything, preview_collection, CollectionPreview, LAST_CHECK_RESULT},
+    config::{Collection, Config, Mikan, Rule, Server, CONFIG_STORE},
+    res::{self, ApiServer},
 };
 use axum::{
-    extract::Query,
+    extract::{Query, Request, State},
     http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{any, get, post},
     Json, Router,
 };
-
-use http::{header, Method};
+use base64::{engine::general_purpose, Engine};
+use color_eyre::eyre::Result;
+use http::{header, HeaderValue, Method};
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tracing::info;
 
-pub async fn serve() {
+pub async fn serve() -> Result<()> {
+    let server = CONFIG_STORE.get()?.0.server.clone().unwrap_or_default();
+    let allow_origin = if server.allowed_origins.is_empty() {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(
+            server
+                .allowed_origins
+                .iter()
+                .map(|o| HeaderValue::from_str(o))
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+    };
     let cors = CorsLayer::new()
         .allow_headers(vec![
             header::ACCEPT,
@@ -25,7 +44,9 @@ pub async fn serve() {
             header::AUTHORIZATION,
             header::CONTENT_LANGUAGE,
             header::CONTENT_TYPE,
+            header::IF_MATCH,
         ])
+        .expose_headers(vec![header::ETAG])
         .allow_methods(vec![
             Method::GET,
             Method::POST,
@@ -37,26 +58,109 @@ pub async fn serve() {
             Method::PATCH,
             Method::TRACE,
         ])
-        // allow requests from any origin
-        .allow_origin(Any);
+        .allow_origin(allow_origin);
 
-    // build our application with a single route
+    // 旧的路由保留, 新的API在 /api/v1 下
     let app = Router::new()
-        .route("/", any(|| async { "Hello, World!" }))
         .route("/request-check", post(request_check))
-        .route("/health/check", get(check_healthy))
         .route("/mikan", get(find_mikan))
         .route("/add-mikan", post(add_mikan))
         .route("/rm-mikan", post(rm_mikan))
         .route("/collection", get(find_collection))
         .route("/add-collection", post(add_collection))
-        .route("/rm-collection", post(rm_collection));
+        .route("/rm-collection", post(rm_collection))
+        .route("/preview-collection", post(preview_collection_handler))
+        .route("/rule", get(find_rule))
+        .route("/rule-options", get(rule_options))
+        .route("/add-rule", post(add_rule))
+        .route("/update-rule", post(update_rule))
+        .route("/rm-rule", post(rm_rule))
+        .nest("/api/v1", v1::router())
+        .route_layer(middleware::from_fn_with_state(
+            Arc::new(Auth::new(&server)),
+            require_auth,
+        ))
+        // 不需要认证
+        .route("/", any(|| async { "Hello, World!" }))
+        .route("/health/check", get(check_healthy))
+        .layer(cors);
+
+    let bind = server.bind.as_deref().unwrap_or("0.0.0.0");
+    let port = server.port.unwrap_or(3000);
+    let listener = TcpListener::bind((bind, port)).await?;
+    match &server.tls {
+        Some(tls) => {
+            info!("Listening on https://{bind}:{port}");
+            tls::serve_tls(listener, app, tls).await?;
+        }
+        None => {
+            info!("Listening on http://{bind}:{port}");
+            axum::serve(listener, app).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 配置中的认证方式, 都没有配置时不需要认证
+struct Auth {
+    /// Authorization 请求头的完整值
+    accepted: Vec<String>,
+    basic: bool,
+}
+
+impl Auth {
+    fn new(server: &Server) -> Self {
+        let mut accepted = vec![];
+        if let Some(token) = &server.token {
+            accepted.push(format!("Bearer {token}"));
+        }
+        if let Some(basic_auth) = &server.basic_auth {
+            let credentials = format!("{}:{}", basic_auth.user, basic_auth.password);
+            accepted.push(format!(
+                "Basic {}",
+                general_purpose::STANDARD.encode(credentials)
+            ));
+        }
+        Auth {
+            accepted,
+            basic: server.basic_auth.is_some(),
+        }
+    }
 
-    // run it with hyper on localhost:3000
-    let port = 3000;
-    let listener = TcpListener::bind(format!("0.0.0.0:{port}")).await.unwrap();
-    info!("Listening on port:{port}");
-    axum::serve(listener, app.layer(cors)).await.unwrap();
+    fn is_authorized(&self, authorization: Option<&[u8]>) -> bool {
+        if self.accepted.is_empty() {
+            return true;
+        }
+        authorization.is_some_and(|value| {
+            self.accepted
+                .iter()
+                .any(|accepted| constant_time_eq(accepted.as_bytes(), value))
+        })
+    }
+}
+
+/// 避免通过响应时间猜测token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn require_auth(State(auth): State<Arc<Auth>>, request: Request, next: Next) -> Response {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .map(HeaderValue::as_bytes);
+    if auth.is_authorized(authorization) {
+        return next.run(request).await;
+    }
+    let mut response =
+        to_resp(StatusCode::UNAUTHORIZED, "unauthorized".to_string()).into_response();
+    if auth.basic {
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            HeaderValue::from_static("Basic realm=\"muuf\""),
+        );
+    }
+    response
 }
 
 #[derive(Serialize)]
@@ -68,30 +172,51 @@ fn to_resp(status: StatusCode, message: String) -> (StatusCode, Json<ApiResponse
     (status, Json(ApiResponse { message }))
 }
 
+/// 旧的路由返回 { "message": "..." }, 出错时的状态码和 /api/v1 一样
+fn legacy<T>(result: v1::ApiResult<T>, message: &str) -> (StatusCode, Json<ApiResponse>) {
+    match result {
+        Ok(_) => to_resp(StatusCode::OK, message.to_string()),
+        Err(e) => to_resp(e.status, e.message),
+    }
+}
+
+type LegacyResult<T> = Result<T, (StatusCode, Json<ApiResponse>)>;
+
+/// 配置文件读取失败(如正在写入)时返回错误, 和 v1 的处理相同
+fn legacy_config() -> LegacyResult<Arc<Config>> {
+    let (config, _) = CONFIG_STORE.get().map_err(|e| {
+        let e = v1::ApiError::from(e);
+        to_resp(e.status, e.message)
+    })?;
+    Ok(config)
+}
+
+/// 没有查询参数时返回全部, 有时返回匹配的一个, 没有匹配时为 404
+fn legacy_find<T: v1::Resource>(
+    filter: Option<impl Fn(&T) -> bool>,
+) -> LegacyResult<(StatusCode, Json<Vec<T>>)> {
+    let items = v1::all_items::<T>().map_err(|e| to_resp(e.status, e.message))?;
+    let Some(filter) = filter else {
+        return Ok((StatusCode::OK, Json(items)));
+    };
+    match items.into_iter().find(filter) {
+        Some(item) => Ok((StatusCode::OK, Json(vec![item]))),
+        None => Ok((StatusCode::NOT_FOUND, Json(vec![]))),
+    }
+}
+
 async fn find_mikan(
     Query(params): Query<HashMap<String, String>>,
-) -> (StatusCode, Json<Vec<Mikan>>) {
-    let config = Config::load().unwrap();
-    if let Some(id) = params.get("bangumiId") {
-        if let Some(m) = config
-            .mikan
-            .iter()
-            .find(|m| m.url.contains(&format!("bangumiId={id}")))
-        {
-            return (StatusCode::OK, Json(vec![m.clone()]));
-        } else {
-            return (StatusCode::NOT_FOUND, Json(vec![]));
-        }
-    }
-    (StatusCode::OK, Json(config.mikan.clone()))
+) -> LegacyResult<(StatusCode, Json<Vec<Mikan>>)> {
+    legacy_find(
+        params
+            .get("bangumiId")
+            .map(|id| move |m: &Mikan| m.url.contains(&format!("bangumiId={id}"))),
+    )
 }
 
 async fn add_mikan(Json(m): Json<Mikan>) -> (StatusCode, Json<ApiResponse>) {
-    let mut config = Config::load().unwrap();
-    match config.add_mikan(m).and_then(|()| config.save()) {
-        Err(e) => to_resp(StatusCode::BAD_REQUEST, e.to_string()),
-        Ok(_) => to_resp(StatusCode::OK, "add success".to_string()),
-    }
+    legacy(v1::upsert(m).await, "add success")
 }
 
 #[derive(Deserialize)]
@@ -100,33 +225,21 @@ struct RmMikanForm {
 }
 
 async fn rm_mikan(Json(form): Json<RmMikanForm>) -> (StatusCode, Json<ApiResponse>) {
-    let mut config = Config::load().unwrap();
-    match config.rm_mikan(&form.url).and_then(|()| config.save()) {
-        Err(e) => to_resp(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
-        Ok(_) => to_resp(StatusCode::OK, "rm sucess".to_string()),
-    }
+    legacy(v1::remove_by_key::<Mikan>(&form.url), "rm sucess")
 }
 
 async fn find_collection(
     Query(params): Query<HashMap<String, String>>,
-) -> (StatusCode, Json<Vec<Collection>>) {
-    let config = Config::load().unwrap();
-    if let Some(url) = params.get("url") {
-        if let Some(c) = config.collections.iter().find(|c| &c.torrent_url == url) {
-            return (StatusCode::OK, Json(vec![c.clone()]));
-        } else {
-            return (StatusCode::NOT_FOUND, Json(vec![]));
-        }
-    }
-    (StatusCode::OK, Json(config.collections.clone()))
+) -> LegacyResult<(StatusCode, Json<Vec<Collection>>)> {
+    legacy_find(
+        params
+            .get("url")
+            .map(|url| move |c: &Collection| &c.torrent_url == url),
+    )
 }
 
 async fn add_collection(Json(c): Json<Collection>) -> (StatusCode, Json<ApiResponse>) {
-    let mut config = Config::load().unwrap();
-    match config.add_collection(c).and_then(|()| config.save()) {
-        Err(e) => to_resp(StatusCode::BAD_REQUEST, e.to_string()),
-        Ok(_) => to_resp(StatusCode::OK, "add success".to_string()),
-    }
+    legacy(v1::upsert(c).await, "add success")
 }
 
 #[derive(Deserialize)]
@@ -135,11 +248,76 @@ struct RmCollectionForm {
 }
 
 async fn rm_collection(Json(form): Json<RmCollectionForm>) -> (StatusCode, Json<ApiResponse>) {
-    let mut config = Config::load().unwrap();
-    match config.rm_collection(&form.url).and_then(|()| config.save()) {
-        Err(e) => to_resp(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
-        Ok(_) => to_resp(StatusCode::OK, "rm sucess".to_string()),
-    }
+    legacy(v1::remove_by_key::<Collection>(&form.url), "rm sucess")
+}
+
+async fn preview_collection_handler(
+    Json(c): Json<Collection>,
+) -> Result<Json<CollectionPreview>, (StatusCode, Json<ApiResponse>)> {
+    let config = legacy_config()?;
+    preview_collection(&c, &config.link)
+        .await
+        .map(Json)
+        .map_err(|e| to_resp(StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+async fn find_rule(
+    Query(params): Query<HashMap<String, String>>,
+) -> LegacyResult<(StatusCode, Json<Vec<Rule>>)> {
+    legacy_find(
+        params
+            .get("name")
+            .map(|name| move |r: &Rule| &r.name == name),
+    )
+}
+
+/// 资源站的字幕组和资源类型, 用于前端的下拉框
+#[derive(Serialize)]
+struct RuleOptions {
+    sub_groups: Vec<(i32, String)>,
+    res_types: Vec<(i32, String)>,
+}
+
+async fn rule_options() -> Result<Json<RuleOptions>, (StatusCode, Json<ApiResponse>)> {
+    let config = legacy_config()?;
+    let res_api = res::get_res_api(&config.res_api);
+    let options = async {
+        Ok::<_, color_eyre::Report>(RuleOptions {
+            sub_groups: res_api.sub_groups().await?,
+            res_types: res_api.res_types().await?,
+        })
+    };
+    options
+        .await
+        .map(Json)
+        .map_err(|e| to_resp(StatusCode::BAD_GATEWAY, e.to_string()))
+}
+
+async fn add_rule(Json(r): Json<Rule>) -> (StatusCode, Json<ApiResponse>) {
+    legacy(v1::upsert(r).await, "add success")
+}
+
+#[derive(Deserialize)]
+struct UpdateRuleForm {
+    /// 修改前的名字
+    name: String,
+    rule: Rule,
+}
+
+async fn update_rule(Json(form): Json<UpdateRuleForm>) -> (StatusCode, Json<ApiResponse>) {
+    legacy(
+        v1::replace_by_key(&form.name, form.rule).await,
+        "update success",
+    )
+}
+
+#[derive(Deserialize)]
+struct RmRuleForm {
+    name: String,
+}
+
+async fn rm_rule(Json(form): Json<RmRuleForm>) -> (StatusCode, Json<ApiResponse>) {
+    legacy(v1::remove_by_key::<Rule>(&form.name), "rm sucess")
 }
 
 async fn request_check() -> (StatusCode, Json<ApiResponse>) {
@@ -158,3 +336,29 @@ async fn check_healthy() -> (StatusCode, Json<ApiResponse>) {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BasicAuth;
+
+    #[test]
+    fn test_auth() {
+        let auth = Auth::new(&Server::default());
+        assert!(auth.is_authorized(None));
+
+        let auth = Auth::new(&Server {
+            token: Some("secret".to_string()),
+            basic_auth: Some(BasicAuth {
+                user: "u".to_string(),
+                password: "p".to_string(),
+            }),
+            ..Default::default()
+        });
+        assert!(!auth.is_authorized(None));
+        assert!(auth.is_authorized(Some(b"Bearer secret")));
+        assert!(!auth.is_authorized(Some(b"Bearer secre")));
+        assert!(auth.is_authorized(Some(b"Basic dTpw")));
+        assert!(!auth.is_authorized(Some(b"Basic dTpx")));
+    }
+}
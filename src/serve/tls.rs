@@ -0,0 +1,61 @@
+use std::{fs::File, io::BufReader, sync::Arc};
+
+use axum::Router;
+use color_eyre::eyre::{eyre, Result};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use tokio::net::TcpListener;
+use tokio_rustls::{
+    rustls::{crypto::ring, ServerConfig},
+    TlsAcceptor,
+};
+use tracing::debug;
+
+use crate::config::Tls;
+
+/// 用本地的证书文件提供HTTPS
+pub async fn serve_tls(listener: TcpListener, app: Router, tls: &Tls) -> Result<()> {
+    let acceptor = TlsAcceptor::from(Arc::new(server_config(tls)?));
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                debug!("accept failed: {e}");
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug!("tls handshake with {addr} failed: {e}");
+                    return;
+                }
+            };
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("connection with {addr} failed: {e}");
+            }
+        });
+    }
+}
+
+fn server_config(tls: &Tls) -> Result<ServerConfig> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(&tls.cert)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&tls.key)?))?
+        .ok_or_else(|| eyre!("没有找到私钥: {}", tls.key))?;
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
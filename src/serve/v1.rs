@@ -0,0 +1,689 @@
+/*
+   /api/v1, 每种订阅都是一个资源
+   GET    /api/v1/{kind}?page=1&per_page=50   列表
+   POST   /api/v1/{kind}                      新建
+   GET    /api/v1/{kind}/{id}                 查询
+   PUT    /api/v1/{kind}/{id}                 替换
+   PATCH  /api/v1/{kind}/{id}                 部分修改 (JSON Merge Patch)
+   DELETE /api/v1/{kind}/{id}                 删除
+   kind 为 mikan collections rules subtitle-packs
+   id 在新建时生成并保存在配置中, 修改订阅的其他字段不会变, 手动添加的订阅在加载配置时按唯一字段生成
+   响应的 ETag 是整个配置的版本, 修改时带上 If-Match, 配置已经被其他请求修改过时返回 412
+*/
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Path, Query,
+    },
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use color_eyre::eyre::{Report, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    checker::{self, Issue, Level},
+    config::{
+        Collection, Config, Mikan, RevisionMismatch, Rule, Subscription, SubtitlePack, CONFIG_STORE,
+    },
+    res,
+};
+
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 500;
+/// 没有带 If-Match 的 PATCH 遇到并发修改时重试的次数
+const PATCH_ATTEMPTS: usize = 3;
+
+pub fn router() -> Router {
+    Router::new()
+        .merge(resource_router::<Mikan>())
+        .merge(resource_router::<Collection>())
+        .merge(resource_router::<Rule>())
+        .merge(resource_router::<SubtitlePack>())
+}
+
+fn resource_router<T: Resource>() -> Router {
+    Router::new()
+        .route(&format!("/{}", T::KIND), get(list::<T>).post(create::<T>))
+        .route(
+            &format!("/{}/:id", T::KIND),
+            get(find::<T>)
+                .put(replace::<T>)
+                .patch(patch::<T>)
+                .delete(remove::<T>),
+        )
+}
+
+/// 配置中可以通过API修改的订阅
+#[async_trait]
+pub(super) trait Resource: Subscription + Send + Sync + 'static {
+    /// 路径中的名字
+    const KIND: &'static str;
+    fn items(config: &Config) -> &Vec<Self>;
+    fn items_mut(config: &mut Config) -> &mut Vec<Self>;
+    /// 保存前检查, 可以补全字段
+    async fn validate(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// 和 muuf validate 相同的检查, 有错误时不保存
+    fn check(&self) -> checker::Report {
+        checker::Report {
+            valid: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl Resource for Mikan {
+    const KIND: &'static str = "mikan";
+    fn items(config: &Config) -> &Vec<Self> {
+        &config.mikan
+    }
+    fn items_mut(config: &mut Config) -> &mut Vec<Self> {
+        &mut config.mikan
+    }
+    fn check(&self) -> checker::Report {
+        checker::validate_mikan(self)
+    }
+}
+
+impl Resource for Collection {
+    const KIND: &'static str = "collections";
+    fn items(config: &Config) -> &Vec<Self> {
+        &config.collections
+    }
+    fn items_mut(config: &mut Config) -> &mut Vec<Self> {
+        &mut config.collections
+    }
+    fn check(&self) -> checker::Report {
+        checker::validate_collection(self)
+    }
+}
+
+#[async_trait]
+impl Resource for Rule {
+    const KIND: &'static str = "rules";
+    fn items(config: &Config) -> &Vec<Self> {
+        &config.rules
+    }
+    fn items_mut(config: &mut Config) -> &mut Vec<Self> {
+        &mut config.rules
+    }
+    async fn validate(&mut self) -> Result<()> {
+        let res_api = res::get_res_api(&self.res_api);
+        res::validate_rule(self, &res_api).await
+    }
+}
+
+impl Resource for SubtitlePack {
+    const KIND: &'static str = "subtitle-packs";
+    fn items(config: &Config) -> &Vec<Self> {
+        &config.subtitle_packs
+    }
+    fn items_mut(config: &mut Config) -> &mut Vec<Self> {
+        &mut config.subtitle_packs
+    }
+    fn check(&self) -> checker::Report {
+        checker::validate_subtitle_pack(self)
+    }
+}
+
+#[derive(Deserialize)]
+struct Pagination {
+    /// 从1开始
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Page<T> {
+    items: Vec<T>,
+    total: usize,
+    page: usize,
+    per_page: usize,
+}
+
+fn paginate<T: Clone>(items: &[T], pagination: &Pagination) -> Page<T> {
+    let page = pagination.page.unwrap_or(1).max(1);
+    let per_page = pagination
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    Page {
+        items: items
+            .iter()
+            .skip((page - 1).saturating_mul(per_page))
+            .take(per_page)
+            .cloned()
+            .collect(),
+        total: items.len(),
+        page,
+        per_page,
+    }
+}
+
+/// 错误都返回 { "code": "not_found", "message": "..." }
+#[derive(Debug, Serialize)]
+pub(super) struct ApiError {
+    #[serde(skip)]
+    pub(super) status: StatusCode,
+    code: &'static str,
+    pub(super) message: String,
+    /// 订阅检查出的问题, 和 muuf validate 的输出相同
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    issues: Vec<Issue>,
+}
+
+impl ApiError {
+    fn not_found<T: Resource>(id: &str) -> Self {
+        ApiError {
+            status: StatusCode::NOT_FOUND,
+            code: "not_found",
+            message: format!("{} {id} not found", T::KIND),
+            issues: vec![],
+        }
+    }
+
+    fn conflict<T: Resource>(item: &T) -> Self {
+        ApiError {
+            status: StatusCode::CONFLICT,
+            code: "conflict",
+            message: format!("{} {} already exists", T::KIND, item.key()),
+            issues: vec![],
+        }
+    }
+
+    fn invalid(message: String) -> Self {
+        ApiError {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            code: "invalid",
+            message,
+            issues: vec![],
+        }
+    }
+
+    /// 检查出错误的订阅, 返回所有的问题
+    fn invalid_item<T: Resource>(report: checker::Report) -> Self {
+        let errors = report
+            .issues
+            .iter()
+            .filter(|issue| issue.level == Level::Error)
+            .map(|issue| format!("{}: {}", issue.path, issue.message))
+            .collect::<Vec<_>>();
+        ApiError {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            code: "invalid",
+            message: format!("invalid {}: {}", T::KIND, errors.join("; ")),
+            issues: report.issues,
+        }
+    }
+
+    fn internal(message: String) -> Self {
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: "internal",
+            message,
+            issues: vec![],
+        }
+    }
+
+    fn precondition_failed(current: &str) -> Self {
+        ApiError {
+            status: StatusCode::PRECONDITION_FAILED,
+            code: "precondition_failed",
+            message: format!("config has been modified, current revision is {current}"),
+            issues: vec![],
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// 修改配置时在闭包中返回的 ApiError 原样返回
+impl From<Report> for ApiError {
+    fn from(report: Report) -> Self {
+        let report = match report.downcast::<ApiError>() {
+            Ok(e) => return e,
+            Err(report) => report,
+        };
+        match report.downcast_ref::<RevisionMismatch>() {
+            Some(e) => ApiError::precondition_failed(&e.current),
+            None => ApiError::internal(report.to_string()),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError {
+            status: rejection.status(),
+            code: "invalid",
+            message: rejection.body_text(),
+            issues: vec![],
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::invalid(rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+pub(super) type ApiResult<T> = Result<T, ApiError>;
+
+/// 带 ETag 的响应
+type Tagged<T> = ([(HeaderName, String); 1], T);
+
+fn tagged<T>(revision: &str, body: T) -> Tagged<T> {
+    ([(header::ETAG, format!("\"{revision}\""))], body)
+}
+
+/// If-Match 中的版本, 没有或者为 * 时不检查
+fn if_match(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::IF_MATCH)?.to_str().ok()?.trim();
+    if value == "*" {
+        return None;
+    }
+    let value = value.strip_prefix("W/").unwrap_or(value);
+    Some(value.trim_matches('"').to_string())
+}
+
+/// 当前时间的纳秒数, 和已有的id重复时加一
+fn new_id<T: Resource>(items: &[T]) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    (nanos..)
+        .map(|n| format!("{n:016x}"))
+        .find(|id| !items.iter().any(|item| item.id() == Some(id)))
+        .unwrap_or_default()
+}
+
+fn position<T: Resource>(config: &Config, id: &str) -> ApiResult<usize> {
+    T::items(config)
+        .iter()
+        .position(|item| item.id() == Some(id))
+        .ok_or_else(|| ApiError::not_found::<T>(id))
+}
+
+/// 旧的路由用唯一字段(mikan的url, collection的torrent_url, rule的name)指定订阅
+fn id_by_key<T: Resource>(key: &str) -> ApiResult<String> {
+    let (config, _) = CONFIG_STORE.get()?;
+    T::items(&config)
+        .iter()
+        .find(|item| item.key() == key)
+        .and_then(|item| item.id().map(str::to_string))
+        .ok_or_else(|| ApiError::not_found::<T>(key))
+}
+
+/// 保存的订阅替换哪一个
+#[derive(Clone, Copy)]
+pub(super) enum Target<'a> {
+    /// 添加到最后
+    New,
+    /// 替换 id 对应的订阅
+    Id(&'a str),
+    /// 替换唯一字段相同的订阅, 没有时添加, 旧的 add 路由使用
+    Key,
+}
+
+/// 检查后按 target 替换或添加订阅, 检查可能需要请求资源站, 在拿写锁之前完成
+pub(super) async fn put_item<T: Resource>(
+    expected: Option<&str>,
+    target: Target<'_>,
+    mut item: T,
+) -> ApiResult<Tagged<Json<T>>> {
+    let report = item.check();
+    if !report.valid {
+        return Err(ApiError::invalid_item::<T>(report));
+    }
+    item.validate()
+        .await
+        .map_err(|e| ApiError::invalid(e.to_string()))?;
+    let (item, revision) = CONFIG_STORE.update(expected, move |config| {
+        Ok(place_item(config, target, item)?)
+    })?;
+    Ok(tagged(&revision, Json(item)))
+}
+
+/**
+ * 按 target 替换或添加订阅, 和其他订阅的唯一字段或id重复时返回 conflict
+ * 替换时保留原来的id, 添加时生成id
+ */
+fn place_item<T: Resource>(config: &mut Config, target: Target, mut item: T) -> ApiResult<T> {
+    let index = match target {
+        Target::New => None,
+        Target::Id(id) => Some(position::<T>(config, id)?),
+        Target::Key => T::items(config)
+            .iter()
+            .position(|other| other.key() == item.key()),
+    };
+    let items = T::items_mut(config);
+    match index.and_then(|index| items[index].id().map(str::to_string)) {
+        Some(id) => *item.id_mut() = Some(id),
+        None if item.id().is_none() => *item.id_mut() = Some(new_id(items)),
+        None => {}
+    }
+    if items.iter().enumerate().any(|(i, other)| {
+        Some(i) != index && (other.key() == item.key() || other.id() == item.id())
+    }) {
+        return Err(ApiError::conflict(&item));
+    }
+    match index {
+        Some(index) => items[index] = item.clone(),
+        None => items.push(item.clone()),
+    }
+    Ok(item)
+}
+
+async fn list<T: Resource>(
+    pagination: Result<Query<Pagination>, QueryRejection>,
+) -> ApiResult<Tagged<Json<Page<T>>>> {
+    let Query(pagination) = pagination?;
+    let (config, revision) = CONFIG_STORE.get()?;
+    let page = paginate(T::items(&config), &pagination);
+    Ok(tagged(
+        &revision,
+        Json(Page {
+            items: page.items.into_iter().collect(),
+            total: page.total,
+            page: page.page,
+            per_page: page.per_page,
+        }),
+    ))
+}
+
+async fn find<T: Resource>(Path(id): Path<String>) -> ApiResult<Tagged<Json<T>>> {
+    let (config, revision) = CONFIG_STORE.get()?;
+    let index = position::<T>(&config, &id)?;
+    Ok(tagged(&revision, Json(T::items(&config)[index].clone())))
+}
+
+async fn create<T: Resource>(
+    headers: HeaderMap,
+    item: Result<Json<T>, JsonRejection>,
+) -> ApiResult<(StatusCode, Tagged<Json<T>>)> {
+    let Json(item) = item?;
+    let created = put_item(if_match(&headers).as_deref(), Target::New, item).await?;
+    Ok((StatusCode::CREATED, created))
+}
+
+async fn replace<T: Resource>(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    item: Result<Json<T>, JsonRejection>,
+) -> ApiResult<Tagged<Json<T>>> {
+    let Json(item) = item?;
+    position::<T>(&CONFIG_STORE.get()?.0, &id)?;
+    put_item(if_match(&headers).as_deref(), Target::Id(&id), item).await
+}
+
+/// patch 基于读到的版本计算, 保存时要求配置没有变过; 没有带 If-Match 时遇到并发修改重新计算
+async fn patch<T: Resource>(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    patch: Result<Json<Value>, JsonRejection>,
+) -> ApiResult<Tagged<Json<T>>> {
+    let Json(patch) = patch?;
+    let expected = if_match(&headers);
+    let mut attempts = 0;
+    loop {
+        let (config, revision) = CONFIG_STORE.get()?;
+        if expected.as_ref().is_some_and(|e| *e != revision) {
+            return Err(ApiError::precondition_failed(&revision));
+        }
+        let index = position::<T>(&config, &id)?;
+        let mut value = serde_json::to_value(&T::items(&config)[index])
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        merge_patch(&mut value, patch.clone());
+        let item = serde_json::from_value(value).map_err(|e| ApiError::invalid(e.to_string()))?;
+        attempts += 1;
+        match put_item(Some(&revision), Target::Id(&id), item).await {
+            Err(e)
+                if e.status == StatusCode::PRECONDITION_FAILED
+                    && expected.is_none()
+                    && attempts < PATCH_ATTEMPTS => {}
+            result => return result,
+        }
+    }
+}
+
+async fn remove<T: Resource>(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<(StatusCode, Tagged<()>)> {
+    let revision = remove_item::<T>(if_match(&headers).as_deref(), &id)?;
+    Ok((StatusCode::NO_CONTENT, tagged(&revision, ())))
+}
+
+fn remove_item<T: Resource>(expected: Option<&str>, id: &str) -> ApiResult<String> {
+    let (_, revision) = CONFIG_STORE.update(expected, |config| Ok(take_item::<T>(config, id)?))?;
+    Ok(revision)
+}
+
+fn take_item<T: Resource>(config: &mut Config, id: &str) -> ApiResult<T> {
+    let index = position::<T>(config, id)?;
+    Ok(T::items_mut(config).remove(index))
+}
+
+// 旧的路由用唯一字段指定订阅, 和上面的处理共用, 只是请求和响应的格式不同
+pub(super) fn all_items<T: Resource>() -> ApiResult<Vec<T>> {
+    Ok(T::items(&CONFIG_STORE.get()?.0).clone())
+}
+
+pub(super) async fn replace_by_key<T: Resource>(key: &str, item: T) -> ApiResult<T> {
+    let id = id_by_key::<T>(key)?;
+    let (_, Json(item)) = put_item(None, Target::Id(&id), item).await?;
+    Ok(item)
+}
+
+/// 旧的 add 路由, 唯一字段相同时替换
+pub(super) async fn upsert<T: Resource>(item: T) -> ApiResult<T> {
+    let (_, Json(item)) = put_item(None, Target::Key, item).await?;
+    Ok(item)
+}
+
+pub(super) fn remove_by_key<T: Resource>(key: &str) -> ApiResult<()> {
+    remove_item::<T>(None, &id_by_key::<T>(key)?)?;
+    Ok(())
+}
+
+/// RFC 7386, patch 中为 null 的字段删除, 对象递归合并, 其他直接替换
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_patch() {
+        let mut target =
+            json!({ "name": "n", "season": 1, "extra": [1], "nested": { "a": 1, "b": 2 } });
+        merge_patch(
+            &mut target,
+            json!({ "season": null, "extra": [2], "nested": { "b": null, "c": 3 } }),
+        );
+        assert_eq!(
+            target,
+            json!({ "name": "n", "extra": [2], "nested": { "a": 1, "c": 3 } })
+        );
+    }
+
+    #[test]
+    fn test_paginate() {
+        let items = (1..=5).collect::<Vec<_>>();
+        let page = paginate(
+            &items,
+            &Pagination {
+                page: Some(2),
+                per_page: Some(2),
+            },
+        );
+        assert_eq!(page.items, vec![3, 4]);
+        assert_eq!(page.total, 5);
+        let page = paginate(
+            &items,
+            &Pagination {
+                page: Some(0),
+                per_page: None,
+            },
+        );
+        assert_eq!((page.page, page.per_page), (1, DEFAULT_PER_PAGE));
+        assert_eq!(page.items.len(), 5);
+    }
+
+    #[test]
+    fn test_if_match() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(if_match(&headers), None);
+        headers.insert(header::IF_MATCH, "\"0a1b2c3d\"".parse().unwrap());
+        assert_eq!(if_match(&headers).as_deref(), Some("0a1b2c3d"));
+        headers.insert(header::IF_MATCH, "W/\"0a1b2c3d\"".parse().unwrap());
+        assert_eq!(if_match(&headers).as_deref(), Some("0a1b2c3d"));
+        headers.insert(header::IF_MATCH, "*".parse().unwrap());
+        assert_eq!(if_match(&headers), None);
+    }
+
+    #[test]
+    fn test_api_error_from_report() {
+        let e = ApiError::from(Report::new(ApiError::invalid("bad".to_string())));
+        assert_eq!(
+            (e.status, e.code),
+            (StatusCode::UNPROCESSABLE_ENTITY, "invalid")
+        );
+        let e = ApiError::from(Report::new(RevisionMismatch {
+            current: "0a1b2c3d".to_string(),
+        }));
+        assert_eq!(e.status, StatusCode::PRECONDITION_FAILED);
+        let e = ApiError::from(color_eyre::eyre::eyre!("io"));
+        assert_eq!(e.status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_check_item() {
+        let mut mikan: Mikan =
+            toml::from_str("url = \"https://mikanani.me/RSS/Bangumi?bangumiId=1\"\nname = \"a\"")
+                .unwrap();
+        assert!(mikan.check().valid);
+        mikan.episode_regex = Some("(".to_string());
+        mikan.season_regex = Some(r"S\d+".to_string());
+        let e = ApiError::invalid_item::<Mikan>(mikan.check());
+        assert_eq!(e.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            e.issues.iter().map(|i| i.path.as_str()).collect::<Vec<_>>(),
+            vec!["episode_regex", "season_regex"]
+        );
+
+        let mut collection: Collection = toml::from_str(
+            "torrent_url = \"https://example.com/a.torrent\"\nname = \"a\"\ntitle = \"a\"\ninclude = [\"[\"]",
+        )
+        .unwrap();
+        assert!(!collection.check().valid);
+        collection.include.clear();
+        assert!(collection.check().valid);
+    }
+
+    #[test]
+    fn test_rule_crud() {
+        let mut config: Config = toml::from_str(
+            r#"
+        check_interval = 10
+        res_api = "dmhy"
+
+        [downloader]
+        type = "transmission"
+        url = "https://192.168.1.1:8080/transmission/rpc"
+        user = "admin"
+        password = "123123"
+        "#,
+        )
+        .unwrap();
+        let rule = |name: &str, keyword: &str| -> Rule {
+            toml::from_str(&format!(
+                "name = \"{name}\"\nkeywords = [\"{keyword}\"]\nres_api = \"dmhy\""
+            ))
+            .unwrap()
+        };
+        let a = place_item(&mut config, Target::New, rule("a", "1")).unwrap();
+        let a = a.id.unwrap();
+        place_item(&mut config, Target::New, rule("b", "2")).unwrap();
+        let e = place_item(&mut config, Target::New, rule("a", "3")).unwrap_err();
+        assert_eq!(e.status, StatusCode::CONFLICT);
+
+        let updated = place_item(&mut config, Target::Id(&a), rule("a", "3")).unwrap();
+        assert_eq!(updated.id.as_deref(), Some(a.as_str()));
+        assert_eq!(config.rules[0].keywords, vec!["3"]);
+        let e = place_item(&mut config, Target::Id(&a), rule("b", "3")).unwrap_err();
+        assert_eq!(e.status, StatusCode::CONFLICT);
+        place_item(&mut config, Target::Id(&a), rule("c", "3")).unwrap();
+        assert_eq!(config.rules[0].name, "c");
+        let e = place_item(&mut config, Target::Id("missing"), rule("a", "3")).unwrap_err();
+        assert_eq!(e.status, StatusCode::NOT_FOUND);
+
+        place_item(&mut config, Target::Key, rule("b", "4")).unwrap();
+        assert_eq!(config.rules[1].keywords, vec!["4"]);
+
+        assert_eq!(take_item::<Rule>(&mut config, &a).unwrap().name, "c");
+        assert!(take_item::<Rule>(&mut config, &a).is_err());
+        assert_eq!(
+            config
+                .rules
+                .iter()
+                .map(|r| r.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+    }
+
+    #[test]
+    fn test_new_id() {
+        let mut rules: Vec<Rule> =
+            vec![toml::from_str("name = \"a\"\nkeywords = [\"a\"]\nres_api = \"dmhy\"").unwrap()];
+        rules[0].id = Some(new_id(&rules));
+        let id = new_id(&rules);
+        assert_eq!(id.len(), 16);
+        assert_ne!(rules[0].id(), Some(id.as_str()));
+    }
+}